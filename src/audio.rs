@@ -0,0 +1,251 @@
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::helpers;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AudioLoadOrder {
+    pub load_order: Vec<String>,
+}
+
+/// Banks never needed by the mod since they are loaded outside of dungeons or for props, matched
+/// anywhere in the bank's path
+const EXCLUDED_BANKS: &[&str] = &["props", "darkestdungeon", "town"];
+
+/// Audio banks needed for the monsters which can spawn, along with the monsters no bank was found for
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequiredBanks {
+    pub load_order: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+/// Read the dungeon audio load order of the base game and of any included DLC
+pub fn read_load_orders(
+    base_path: &Path,
+    dlc_paths: &[&PathBuf],
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let audio_json_path = base_path.join("audio").join("base.dungeon.load_order.json");
+    let audio_data: AudioLoadOrder = serde_json::from_str(&fs::read_to_string(audio_json_path)?)?;
+    let mut load_order = audio_data.load_order;
+
+    for dlc_path in dlc_paths {
+        for audio_dir in helpers::find_dirs(dlc_path, "audio", helpers::DLC_SEARCH_DEPTH) {
+            let mut load_order_files: Vec<PathBuf> = fs::read_dir(&audio_dir)?
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.to_string_lossy().ends_with(".load_order.json"))
+                .collect();
+            load_order_files.sort();
+            for load_order_file in load_order_files {
+                let dlc_audio: AudioLoadOrder =
+                    serde_json::from_str(&fs::read_to_string(&load_order_file)?)?;
+                for entry in dlc_audio.load_order {
+                    if !load_order.contains(&entry) {
+                        load_order.push(entry);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(load_order)
+}
+
+/// Work out which banks the monsters able to spawn need
+/// Banks named after a dungeon or monster are only kept when a spawning monster belongs to that
+/// dungeon in the base game or is that monster, every other bank is shared and always kept.
+pub fn required_banks(
+    load_order: &[String],
+    spawning: &BTreeSet<String>,
+    home_dungeons: &BTreeMap<String, BTreeSet<String>>,
+) -> RequiredBanks {
+    let all_dungeons: BTreeSet<&String> = home_dungeons.values().flatten().collect();
+    let all_families: BTreeSet<&str> = home_dungeons.keys().map(|m| monster_family(m)).collect();
+
+    let needed_dungeons: BTreeSet<&String> = spawning
+        .iter()
+        .filter_map(|monster| home_dungeons.get(monster))
+        .flatten()
+        .collect();
+    let needed_families: BTreeSet<&str> = spawning.iter().map(|m| monster_family(m)).collect();
+
+    let mut required = RequiredBanks::default();
+    for entry in load_order {
+        let path_words = bank_words(entry, false);
+        if EXCLUDED_BANKS
+            .iter()
+            .any(|excluded| names(&path_words, excluded))
+        {
+            continue;
+        }
+        let words = bank_words(entry, true);
+        let for_dungeons: Vec<&&String> = all_dungeons
+            .iter()
+            .filter(|dungeon| names(&words, dungeon))
+            .collect();
+        let for_families: Vec<&&str> = all_families
+            .iter()
+            .filter(|family| names(&words, family))
+            .collect();
+        let shared = for_dungeons.is_empty() && for_families.is_empty();
+        let needed = for_dungeons.iter().any(|d| needed_dungeons.contains(**d))
+            || for_families.iter().any(|f| needed_families.contains(**f));
+        if (shared || needed) && !required.load_order.contains(entry) {
+            required.load_order.push(entry.clone());
+        }
+    }
+
+    for monster in spawning {
+        let family = monster_family(monster);
+        let has_bank = load_order.iter().any(|entry| {
+            let words = bank_words(entry, true);
+            names(&words, family)
+                || home_dungeons
+                    .get(monster)
+                    .is_some_and(|dungeons| dungeons.iter().any(|d| names(&words, d)))
+        });
+        if !has_bank {
+            warn!("No audio bank found for monster {}", monster);
+            required.missing.push(monster.clone());
+        }
+    }
+
+    required
+}
+
+pub fn render(load_order: &[String]) -> Result<String, Box<dyn Error>> {
+    Ok(serde_json::to_string(&AudioLoadOrder {
+        load_order: load_order.to_vec(),
+    })?)
+}
+
+/// Monster name without its level tier, e.g. `cultist_brawler` for `cultist_brawler_A`
+fn monster_family(monster: &str) -> &str {
+    match monster.rsplit_once('_') {
+        Some((family, tier)) if tier.len() == 1 => family,
+        _ => monster,
+    }
+}
+
+/// Lower case words of each path segment of a bank entry, e.g. `[["audio"], ["dungeon", "crypts"]]`
+/// for `audio/dungeon_crypts.bank`. With `file_only` only the file name is split, as directories
+/// could otherwise match a dungeon name.
+fn bank_words(entry: &str, file_only: bool) -> Vec<Vec<String>> {
+    let entry = entry.to_lowercase();
+    let entry = entry.strip_suffix(".bank").unwrap_or(&entry);
+    let segments: Vec<&str> = entry.split(['/', '\\']).collect();
+    let skip = if file_only { segments.len() - 1 } else { 0 };
+    segments
+        .into_iter()
+        .skip(skip)
+        .map(|segment| {
+            segment
+                .split(['_', '-', '.', ' '])
+                .filter(|word| !word.is_empty())
+                .map(|word| word.to_string())
+                .collect()
+        })
+        .collect()
+}
+
+/// Whether a name is found in one of the segments as whole words, so `cove` names
+/// `dungeon_cove` but not `mon_covenant`
+fn names(segments: &[Vec<String>], name: &str) -> bool {
+    let name_words: Vec<&str> = name.split('_').collect();
+    segments.iter().any(|words| {
+        words
+            .windows(name_words.len())
+            .any(|window| window.iter().zip(&name_words).all(|(w, n)| w == n))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_home_dungeons() -> BTreeMap<String, BTreeSet<String>> {
+        [
+            ("skeleton_soldier_A", "crypts"),
+            ("necromancer_A", "crypts"),
+            ("pelagic_grouper_A", "cove"),
+            ("swine_wretch_A", "warrens"),
+        ]
+        .into_iter()
+        .map(|(monster, dungeon)| (monster.to_string(), BTreeSet::from([dungeon.to_string()])))
+        .collect()
+    }
+
+    #[test]
+    fn test_required_banks() {
+        let load_order: Vec<String> = [
+            "audio/master.bank",
+            "audio/dungeon_crypts.bank",
+            "audio/dungeon_cove.bank",
+            "audio/dungeon_warrens.bank",
+            "audio/mon_necromancer.bank",
+            "audio/town_props.bank",
+        ]
+        .iter()
+        .map(|entry| entry.to_string())
+        .collect();
+        let spawning = BTreeSet::from([
+            String::from("skeleton_soldier_A"),
+            String::from("pelagic_grouper_A"),
+            String::from("modded_monster_A"),
+        ]);
+
+        let required = required_banks(&load_order, &spawning, &test_home_dungeons());
+        assert_eq!(
+            required.load_order,
+            vec![
+                "audio/master.bank",
+                "audio/dungeon_crypts.bank",
+                "audio/dungeon_cove.bank"
+            ]
+        );
+        assert_eq!(required.missing, vec!["modded_monster_A"]);
+    }
+
+    #[test]
+    fn test_required_banks_match_whole_words() {
+        let load_order: Vec<String> = [
+            "audio/dungeon_cove.bank",
+            "audio/mon_covenant_priest.bank",
+            "audio/mon_townsfolk.bank",
+            "audio/town/dungeon_crypts.bank",
+            "audio/Necromancer_Boss.bank",
+        ]
+        .iter()
+        .map(|entry| entry.to_string())
+        .collect();
+        let mut home_dungeons = test_home_dungeons();
+        home_dungeons.insert(
+            String::from("covenant_priest_A"),
+            BTreeSet::from([String::from("weald")]),
+        );
+        let spawning = BTreeSet::from([String::from("swine_wretch_A")]);
+
+        // `cove` and `town` must not claim the covenant and townsfolk banks
+        let required = required_banks(&load_order, &spawning, &home_dungeons);
+        assert_eq!(required.load_order, vec!["audio/mon_townsfolk.bank"]);
+        assert_eq!(required.missing, vec!["swine_wretch_A"]);
+
+        let spawning = BTreeSet::from([String::from("necromancer_A")]);
+        let required = required_banks(&load_order, &spawning, &home_dungeons);
+        assert_eq!(
+            required.load_order,
+            vec!["audio/mon_townsfolk.bank", "audio/Necromancer_Boss.bank"]
+        );
+    }
+
+    #[test]
+    fn test_monster_family() {
+        assert_eq!(monster_family("cultist_brawler_A"), "cultist_brawler");
+        assert_eq!(monster_family("necromancer_C"), "necromancer");
+        assert_eq!(monster_family("bone_rabble"), "bone_rabble");
+    }
+}
@@ -1,4 +1,5 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
 pub struct Opts {
@@ -9,4 +10,51 @@ pub struct Opts {
     /// Print version information
     #[clap(short, long)]
     pub version: bool,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Compare two seeds, or the installed mod against the mod generated again
+    Diff {
+        /// Up to two seeds, with one seed or none the installed mod is compared against it
+        #[clap(num_args = 0..=2)]
+        seeds: Vec<String>,
+
+        /// JSON file with the randomization options, defaults to the options of the installed mod
+        #[clap(long)]
+        settings: Option<PathBuf>,
+
+        /// Game installation directory, defaults to the autodetected installation
+        #[clap(long)]
+        game_dir: Option<PathBuf>,
+    },
+    /// Generate the mod without starting the GUI
+    Generate {
+        /// Seed to generate the mod from, a random seed is used if not given
+        #[clap(long)]
+        seed: Option<String>,
+
+        /// JSON file with the randomization options, as recorded in the seed history
+        #[clap(long)]
+        settings: Option<PathBuf>,
+
+        /// Game installation directory, defaults to the autodetected installation
+        #[clap(long)]
+        game_dir: Option<PathBuf>,
+
+        /// Print the randomization results without installing the mod
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Print the recorded seed history
+    History,
+    /// Print the race verification hash of the installed mod
+    RaceHash {
+        /// Mod directory to hash, defaults to the autodetected installation
+        #[clap(long)]
+        mod_dir: Option<PathBuf>,
+    },
 }
@@ -0,0 +1,191 @@
+use std::fmt;
+
+/// A single line of a `.darkest` data file in the form `key: values .property values .property values`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub key: String,
+    /// Values following the key before any property, e.g. the weight and monsters of mash lines
+    pub values: Vec<String>,
+    pub props: Vec<(String, Vec<String>)>,
+}
+
+impl Entry {
+    /// Parse a line into an entry, returns `None` for blank, comment or otherwise invalid lines
+    pub fn parse(line: &str) -> Option<Entry> {
+        let (key, rest) = line.split_once(':')?;
+        let key = key.trim();
+        if key.is_empty() || key.starts_with("//") || key.contains(char::is_whitespace) {
+            return None;
+        }
+
+        let mut entry = Entry {
+            key: key.to_string(),
+            values: Vec::new(),
+            props: Vec::new(),
+        };
+        for token in tokenize(rest) {
+            if is_property(&token) {
+                entry.props.push((token[1..].to_string(), Vec::new()));
+            } else if let Some((_, values)) = entry.props.last_mut() {
+                values.push(token);
+            } else {
+                entry.values.push(token);
+            }
+        }
+
+        Some(entry)
+    }
+
+    /// All values of the first property with the given name
+    pub fn get(&self, name: &str) -> Option<&[String]> {
+        self.props
+            .iter()
+            .find(|(pname, _)| pname == name)
+            .map(|(_, values)| values.as_slice())
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Vec<String>> {
+        self.props
+            .iter_mut()
+            .find(|(pname, _)| pname == name)
+            .map(|(_, values)| values)
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.key)?;
+        for value in &self.values {
+            write!(f, " {}", value)?;
+        }
+        for (name, values) in &self.props {
+            write!(f, " .{}", name)?;
+            for value in values {
+                write!(f, " {}", value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A numeric value which may be written as a percentage such as `33%`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Number {
+    pub value: f64,
+    pub percent: bool,
+}
+
+impl Number {
+    pub fn parse(token: &str) -> Option<Number> {
+        match token.strip_suffix('%') {
+            Some(value) => value.parse().ok().map(|value| Number {
+                value,
+                percent: true,
+            }),
+            None => token.parse().ok().map(|value| Number {
+                value,
+                percent: false,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the game files only use whole numbers for percentages and most plain values
+        // keep up to two decimal places for anything else without trailing zeroes
+        let rendered = format!("{:.2}", self.value);
+        let rendered = rendered.trim_end_matches('0').trim_end_matches('.');
+        let rendered = if rendered == "-0" { "0" } else { rendered };
+        write!(f, "{}{}", rendered, if self.percent { "%" } else { "" })
+    }
+}
+
+/// Split a line into tokens keeping quoted strings, which may contain spaces, together
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+/// Property names start with a period followed by a letter, as opposed to values such as `.5`
+fn is_property(token: &str) -> bool {
+    let mut chars = token.chars();
+    chars.next() == Some('.') && chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entry() {
+        let entry =
+            Entry::parse(r#"weapon: .name "crusader_weapon_0" .atk 0% .dmg 6 12 .crit 3% .spd 1"#)
+                .unwrap();
+        assert_eq!(entry.key, "weapon");
+        assert!(entry.values.is_empty());
+        assert_eq!(
+            entry.get("name"),
+            Some(&[String::from("\"crusader_weapon_0\"")][..])
+        );
+        assert_eq!(
+            entry.get("dmg"),
+            Some(&[String::from("6"), String::from("12")][..])
+        );
+        assert_eq!(entry.get("missing"), None);
+    }
+
+    #[test]
+    fn test_parse_entry_values_and_quotes() {
+        let entry =
+            Entry::parse(r#"hall: 4 cultist_brawler_A .note "two words" .chance .5"#).unwrap();
+        assert_eq!(entry.values, vec!["4", "cultist_brawler_A"]);
+        assert_eq!(
+            entry.get("note"),
+            Some(&[String::from("\"two words\"")][..])
+        );
+        assert_eq!(entry.get("chance"), Some(&[String::from(".5")][..]));
+    }
+
+    #[test]
+    fn test_parse_invalid_lines() {
+        assert_eq!(Entry::parse(""), None);
+        assert_eq!(Entry::parse("// comment: here"), None);
+        assert_eq!(Entry::parse("no colon here"), None);
+    }
+
+    #[test]
+    fn test_render_roundtrip() {
+        let line = r#"armour: .name "crusader_armour_0" .def 5% .prot 0 .hp 33 .spd 0"#;
+        assert_eq!(Entry::parse(line).unwrap().to_string(), line);
+    }
+
+    #[test]
+    fn test_number_parse_and_render() {
+        let pct = Number::parse("67%").unwrap();
+        assert!(pct.percent);
+        assert_eq!(pct.value, 67.0);
+        assert_eq!(pct.to_string(), "67%");
+        assert_eq!(Number::parse("0.25").unwrap().to_string(), "0.25");
+        assert_eq!(Number::parse("\"name\""), None);
+    }
+}
@@ -0,0 +1,258 @@
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::darkest::Entry;
+use crate::localization::StringTable;
+use crate::plan::{self, ModPlan};
+
+/// Differences found in a single mod file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiff {
+    pub path: String,
+    pub changes: Vec<String>,
+}
+
+/// Compare the game data of two generated mods
+/// Files are compared on their parsed data so ordering and formatting differences are ignored.
+pub fn compare(old: &ModPlan, new: &ModPlan) -> Vec<FileDiff> {
+    let old_files = game_data(old);
+    let new_files = game_data(new);
+    let paths: BTreeSet<&String> = old_files.keys().chain(new_files.keys()).collect();
+
+    let mut diffs: Vec<FileDiff> = Vec::new();
+    for path in paths {
+        let changes = match (old_files.get(path), new_files.get(path)) {
+            (Some(old_content), Some(new_content)) => compare_file(path, old_content, new_content),
+            (Some(_), None) => vec![String::from("only in the first mod")],
+            (None, Some(_)) => vec![String::from("only in the second mod")],
+            (None, None) => Vec::new(),
+        };
+        if !changes.is_empty() {
+            diffs.push(FileDiff {
+                path: path.clone(),
+                changes,
+            });
+        }
+    }
+
+    diffs
+}
+
+/// Readable listing of the differences
+pub fn render(diffs: &[FileDiff]) -> String {
+    if diffs.is_empty() {
+        return String::from("No differences\n");
+    }
+    let mut rendered = String::new();
+    for diff in diffs {
+        rendered.push_str(&format!("{}:\n", diff.path));
+        for change in &diff.changes {
+            rendered.push_str(&format!("  {}\n", change));
+        }
+    }
+    rendered
+}
+
+/// Extensions of the parsed game data files, copied assets such as icons are not compared
+const DATA_EXTENSIONS: &[&str] = &[".darkest", ".json", ".xml"];
+
+/// Generated files which hold game data, keyed by their path within the mod
+fn game_data(mod_plan: &ModPlan) -> BTreeMap<String, String> {
+    mod_plan
+        .files
+        .iter()
+        .map(|(path, content)| {
+            (
+                plan::relative_name(path),
+                String::from_utf8_lossy(content).to_string(),
+            )
+        })
+        .filter(|(path, _)| {
+            !plan::is_metadata(path) && DATA_EXTENSIONS.iter().any(|ext| path.ends_with(ext))
+        })
+        .collect()
+}
+
+fn compare_file(path: &str, old: &str, new: &str) -> Vec<String> {
+    if path.ends_with(".darkest") {
+        compare_darkest(old, new)
+    } else if path.ends_with(".json") {
+        match (serde_json::from_str(old), serde_json::from_str(new)) {
+            (Ok(old_value), Ok(new_value)) => {
+                let mut changes: Vec<String> = Vec::new();
+                compare_json("", &old_value, &new_value, &mut changes);
+                changes
+            }
+            _ => compare_text(old, new),
+        }
+    } else if path.ends_with(".string_table.xml") {
+        match (StringTable::parse(old), StringTable::parse(new)) {
+            (Ok(old_table), Ok(new_table)) => compare_string_tables(&old_table, &new_table),
+            _ => compare_text(old, new),
+        }
+    } else {
+        compare_text(old, new)
+    }
+}
+
+/// Compare the entries of two `.darkest` files regardless of their order or spacing
+fn compare_darkest(old: &str, new: &str) -> Vec<String> {
+    let count_entries = |content: &str| {
+        let mut counts: BTreeMap<String, i32> = BTreeMap::new();
+        for entry in content.lines().filter_map(Entry::parse) {
+            *counts.entry(entry.to_string()).or_default() += 1;
+        }
+        counts
+    };
+    let mut counts = count_entries(old);
+    for (line, count) in count_entries(new) {
+        *counts.entry(line).or_default() -= count;
+    }
+
+    let mut changes: Vec<String> = Vec::new();
+    for (line, count) in &counts {
+        for _ in 0..count.abs() {
+            changes.push(format!("{} {}", if *count > 0 { "-" } else { "+" }, line));
+        }
+    }
+    changes
+}
+
+/// Compare two JSON values, describing each difference by its path
+/// Arrays of objects with an `id` are matched by id so reordering is not reported.
+fn compare_json(path: &str, old: &Value, new: &Value, changes: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            let keys: BTreeSet<&String> = old_map.keys().chain(new_map.keys()).collect();
+            for key in keys {
+                let key_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                compare_json(
+                    &key_path,
+                    old_map.get(key).unwrap_or(&Value::Null),
+                    new_map.get(key).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items))
+            if old_items
+                .iter()
+                .chain(new_items)
+                .all(|v| v.get("id").is_some()) =>
+        {
+            let by_id = |items: &[Value]| -> BTreeMap<String, Value> {
+                items
+                    .iter()
+                    .map(|item| (item["id"].to_string(), item.clone()))
+                    .collect()
+            };
+            let old_by_id = by_id(old_items);
+            let new_by_id = by_id(new_items);
+            let ids: BTreeSet<&String> = old_by_id.keys().chain(new_by_id.keys()).collect();
+            for id in ids {
+                compare_json(
+                    &format!("{}[{}]", path, id.trim_matches('"')),
+                    old_by_id.get(id).unwrap_or(&Value::Null),
+                    new_by_id.get(id).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        _ if old != new => changes.push(format!("{}: {} -> {}", path, old, new)),
+        _ => (),
+    }
+}
+
+fn compare_string_tables(old: &StringTable, new: &StringTable) -> Vec<String> {
+    let mut changes: Vec<String> = Vec::new();
+    let langs: BTreeSet<&String> = old.languages.keys().chain(new.languages.keys()).collect();
+    for lang in langs {
+        let empty = BTreeMap::new();
+        let old_entries = old.languages.get(lang).unwrap_or(&empty);
+        let new_entries = new.languages.get(lang).unwrap_or(&empty);
+        let ids: BTreeSet<&String> = old_entries.keys().chain(new_entries.keys()).collect();
+        for id in ids {
+            let (old_text, new_text) = (old_entries.get(id), new_entries.get(id));
+            if old_text != new_text {
+                changes.push(format!(
+                    "{} {}: {} -> {}",
+                    lang,
+                    id,
+                    old_text.map_or("(none)", |t| t.as_str()),
+                    new_text.map_or("(none)", |t| t.as_str())
+                ));
+            }
+        }
+    }
+    changes
+}
+
+fn compare_text(old: &str, new: &str) -> Vec<String> {
+    if old.trim() == new.trim() {
+        Vec::new()
+    } else {
+        vec![String::from("contents differ")]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_darkest_ignores_order_and_spacing() {
+        let old = "hall: .chance 2 .types a_A b_A\nroom: .chance 1 .types c_A\n";
+        let new = "room:  .chance 1   .types c_A\nhall: .chance 2 .types a_A b_A\n";
+        assert!(compare_darkest(old, new).is_empty());
+
+        let changed = "hall: .chance 2 .types a_A d_A\nroom: .chance 1 .types c_A\n";
+        assert_eq!(
+            compare_darkest(old, changed),
+            vec![
+                "- hall: .chance 2 .types a_A b_A",
+                "+ hall: .chance 2 .types a_A d_A"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_json_by_id() {
+        let old: Value = serde_json::from_str(
+            r#"{"skills": [{"id": "a", "hero_classes": ["x"]}, {"id": "b", "hero_classes": ["y"]}]}"#,
+        )
+        .unwrap();
+        let new: Value = serde_json::from_str(
+            r#"{"skills": [{"id": "b", "hero_classes": ["y"]}, {"id": "a", "hero_classes": ["z"]}]}"#,
+        )
+        .unwrap();
+        let mut changes: Vec<String> = Vec::new();
+        compare_json("", &old, &new, &mut changes);
+        assert_eq!(changes, vec![r#"skills[a].hero_classes: ["x"] -> ["z"]"#]);
+    }
+
+    #[test]
+    fn test_compare_skips_metadata() {
+        let mut old = ModPlan::default();
+        old.add_file("seed.txt", "one");
+        old.add_file("heroes/a/a.info.darkest", "weapon: .atk 1");
+        let mut new = ModPlan::default();
+        new.add_file("seed.txt", "two");
+        new.add_file("heroes/a/a.info.darkest", "weapon: .atk 1");
+        new.add_file("dungeons/c/c.1.mash.darkest", "hall: 1 c_A");
+        new.add_file("heroes/a/icons_equip/a.ability.one.png", "icon");
+
+        let diffs = compare(&old, &new);
+        assert_eq!(
+            diffs,
+            vec![FileDiff {
+                path: String::from("dungeons/c/c.1.mash.darkest"),
+                changes: vec![String::from("only in the second mod")],
+            }]
+        );
+        assert_eq!(render(&[]), "No differences\n");
+    }
+}
@@ -0,0 +1,433 @@
+use log::*;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::audio;
+use crate::helpers::{self, GamePath};
+use crate::localization::{self, StringTable};
+use crate::plan::ModPlan;
+use crate::race::{self, RaceHash};
+use crate::rand_enemy::{self, assets::AssetIndex, threat::Balancer};
+use crate::rand_hero::{self, camping_skills, equipment, hero_stats};
+use crate::seed;
+use crate::settings::Settings;
+use crate::spoiler::Spoiler;
+
+/// A mod generated in memory along with its spoiler
+#[derive(Debug, Default, Clone)]
+pub struct Generated {
+    pub plan: ModPlan,
+    pub spoiler: Spoiler,
+}
+
+/// Run the randomizers for the seed and options without writing anything
+/// Errors are logged and returned as a message suitable for the status text.
+pub fn build(gpaths: &GamePath, seed_val: &str, settings: &Settings) -> Result<Generated, String> {
+    let seed_rng = seed::create_rng(seed_val);
+    let mut plan = ModPlan::default();
+    let mut spoiler = Spoiler {
+        seed: seed_val.to_string(),
+        generator: seed::GENERATOR_VERSION,
+        settings: Some(settings.clone()),
+        ..Default::default()
+    };
+    info!("Using seed: {}", seed_val);
+
+    // The seed file can be used with OBS or a bot to display the seed for viewers.
+    plan.add_file("seed.txt", seed_val);
+
+    if settings.rand_camping_skills {
+        let skills_file_path = &gpaths
+            .base
+            .join("raid")
+            .join("camping")
+            .join("default.camping_skills.json");
+        let skills = camping_skills::parse_from_file(skills_file_path)
+            .map_err(|e| fail("Unable to read camping skills data", e))?;
+        info!(
+            "Successfully read camping skill data from: {}",
+            &skills_file_path.display().to_string()
+        );
+        let camping_options = settings.camping_options();
+        let skill_data = camping_skills::randomize(skills, &camping_options, seed_rng.clone())
+            .map_err(|e| fail("Unable to randomize camping skills data", e))?;
+        spoiler.camping_skills = camping_skills::summarize(&skill_data, &camping_options);
+        let rendered = camping_skills::render(&skill_data)
+            .map_err(|e| fail("Unable to render randomized camping skills data", e))?;
+        plan.add_file(
+            Path::new("raid")
+                .join("camping")
+                .join("default.camping_skills.json"),
+            rendered,
+        );
+    }
+
+    if settings.randomizes_heroes() {
+        let mod_heroes = relative(gpaths, &gpaths.mod_heroes);
+
+        // modded heroes join the randomized pool only when requested
+        let mut hero_paths = gpaths.base_heroes.clone();
+        if settings.rand_modded_heroes {
+            hero_paths.extend(gpaths.modded_heroes.clone());
+        }
+
+        let files = rand_hero::combat_skills::get_data_files(&hero_paths, &None)
+            .map_err(|e| fail("Unable to find hero data", e))?;
+        let mut heroes = rand_hero::combat_skills::extract_data(&files);
+
+        if settings.rand_equipment {
+            // upgrade trees live in `upgrades/heroes` next to the `heroes` directory of each class
+            let mut upgrade_dirs: Vec<PathBuf> = vec![gpaths.base.join("upgrades").join("heroes")];
+            for hero_dir in hero_paths.values() {
+                if let Some(root) = hero_dir.parent().and_then(|heroes_dir| heroes_dir.parent()) {
+                    upgrade_dirs.push(root.join("upgrades").join("heroes"));
+                }
+            }
+            upgrade_dirs.sort();
+            upgrade_dirs.dedup();
+            let levels = equipment::read_upgrade_levels(&upgrade_dirs);
+            heroes = equipment::randomize(
+                heroes,
+                &levels,
+                &settings.equipment_options(),
+                seed_rng.clone(),
+            );
+        }
+
+        // stats are adjusted in place so they are written out along with the hero's skills
+        // equipment is swapped first so each class' stat changes apply to the tiers it ends up with
+        if settings.rand_hero_stats {
+            heroes = hero_stats::randomize(heroes, &settings.stat_options(), seed_rng.clone());
+        }
+
+        if settings.rand_combat_skills {
+            let linked_groups = rand_hero::combat_skills::get_linked_skill_groups(&hero_paths);
+
+            let localization_map = rand_hero::combat_skills::randomize(
+                &mut plan,
+                &hero_paths,
+                &mod_heroes,
+                heroes,
+                &linked_groups,
+                seed_rng.clone(),
+            );
+            spoiler.hero_skills = rand_hero::combat_skills::summarize(&localization_map);
+
+            info!("Extracting localization data");
+            let translations =
+                rand_hero::combat_skills::extract_localizations(&gpaths.base, &hero_paths)
+                    .map_err(|e| fail("Unable to read localization data", e))?;
+            info!("Rendering new localization XML");
+            let rendered_tables =
+                rand_hero::combat_skills::render_localizations(translations, localization_map)
+                    .map_err(|e| fail("Unable to render localization data", e))?;
+            // each language gets its own string table which the workshop tool
+            // converts to a matching .loc2 file
+            let mod_localization = relative(gpaths, &gpaths.mod_localization);
+            for (lang, rendered) in rendered_tables {
+                plan.add_file(
+                    mod_localization.join(format!("rand_hero_{}.string_table.xml", lang)),
+                    rendered,
+                );
+            }
+        } else {
+            rand_hero::combat_skills::write_data(&mut plan, &mod_heroes, &heroes);
+        }
+    }
+
+    // DLC dungeons are opt-in pools mixed in with the base game dungeons, their monsters are also
+    // randomized when only monster stats or skills are
+    let mut dungeon_paths = gpaths.base_dungeon.clone();
+    let mut dlc_paths: Vec<&PathBuf> = Vec::new();
+    if settings.randomizes_spawns() || settings.randomizes_monsters() {
+        for dungeon_name in settings.dlc_dungeons() {
+            match (
+                gpaths.dlc_dungeon.get(dungeon_name),
+                gpaths.dlc_dirs.get(dungeon_name),
+            ) {
+                (Some(dungeon_path), Some(dlc_path)) => {
+                    dungeon_paths.insert(dungeon_name.to_string(), dungeon_path.clone());
+                    dlc_paths.push(dlc_path);
+                }
+                _ => warn!(
+                    "DLC dungeon '{}' was not found and is left out of randomization",
+                    dungeon_name
+                ),
+            }
+        }
+    }
+
+    // the base game spawns are needed for dungeon families, spawn shuffling and the audio banks
+    let mashes = if settings.randomizes_spawns() || settings.rand_monster_skills {
+        rand_enemy::mash::get_data_files(&dungeon_paths, &None)
+            .and_then(|files| rand_enemy::mash::extract_data(&gpaths.base, &files))
+            .map_err(|e| fail("Unable to read dungeon spawn data", e))?
+    } else {
+        Vec::new()
+    };
+
+    // monsters are also read for balancing so spawns are scored on the stats they will have in the mod
+    // bosses are never balanced so there is nothing to balance when only they are randomized
+    let balance_options = settings
+        .balance_options()
+        .filter(|_| settings.rand_monster || settings.rand_stall || settings.rand_named);
+    let mut balancer: Option<Balancer> = None;
+    let mut monsters_dirs = vec![gpaths.base.join("monsters")];
+    for dlc_path in &dlc_paths {
+        monsters_dirs.extend(helpers::dlc_monster_dirs(dlc_path));
+    }
+    if settings.randomizes_monsters() || balance_options.is_some() {
+        let mut monsters: Vec<rand_enemy::monster::Monster> = Vec::new();
+        for monsters_dir in &monsters_dirs {
+            let files = rand_enemy::monster::get_data_files(monsters_dir)
+                .map_err(|e| fail("Unable to find monster data", e))?;
+            monsters.extend(
+                rand_enemy::monster::extract_data(&gpaths.base, &files)
+                    .map_err(|e| fail("Unable to read monster data", e))?,
+            );
+        }
+
+        if settings.rand_monster_stats {
+            monsters = rand_enemy::monster::randomize(
+                monsters,
+                &settings.monster_options(),
+                seed_rng.clone(),
+            );
+        }
+
+        if settings.rand_monster_skills {
+            // dungeon families come from the base game spawns, not the randomized ones
+            let families = rand_enemy::mash::monster_families(&mashes);
+            (monsters, spoiler.monster_skills) =
+                rand_enemy::monster_skills::randomize(monsters, &families, seed_rng.clone());
+        }
+
+        if settings.randomizes_monsters() {
+            rand_enemy::monster::write_data(&mut plan, &monsters);
+        }
+        balancer = balance_options.map(|options| Balancer::new(&monsters, options));
+    }
+
+    if settings.randomizes_spawns() {
+        let home_dungeons = rand_enemy::mash::monster_dungeons(&mashes);
+        // spawns which could crash the game or be invisible are left in place, without the index
+        // every spawn is shuffled as before
+        let assets = match AssetIndex::read(&monsters_dirs, &dungeon_paths) {
+            Ok(assets) => Some(assets),
+            Err(e) => {
+                warn!(
+                    "Unable to check monster assets, spawns are shuffled without validation\nReason: {}",
+                    e
+                );
+                None
+            }
+        };
+        let spawns = rand_enemy::mash::randomize(
+            &mut plan,
+            mashes,
+            seed_rng.clone(),
+            &settings.mash_options(),
+            balancer.as_ref(),
+            assets.as_ref(),
+        )
+        .map_err(|e| fail("Unable to randomize dungeon spawns", e))?;
+        spoiler.dungeons = spawns.encounters;
+        spoiler.stall_encounters = spawns.stall;
+        spoiler.named_encounters = spawns.named;
+
+        // monsters moved to another dungeon need their art in that dungeon's asset load list
+        if let Some(assets) = &assets {
+            let mut level_monsters = spoiler.dungeons.clone();
+            for (level, monsters) in spoiler
+                .stall_encounters
+                .iter()
+                .chain(&spoiler.named_encounters)
+            {
+                level_monsters
+                    .entry(level.clone())
+                    .or_default()
+                    .extend(monsters.iter().cloned());
+            }
+            for (dungeon, entries) in assets.required_entries(&level_monsters, &home_dungeons) {
+                // the list goes next to the dungeon's own lists, within its DLC for DLC dungeons
+                let Some(dungeon_dir) = dungeon_paths
+                    .get(&dungeon)
+                    .and_then(|path| path.strip_prefix(&gpaths.base).ok())
+                else {
+                    continue;
+                };
+                match rand_enemy::assets::render(&entries) {
+                    Ok(rendered) => plan.add_file(
+                        dungeon_dir.join(format!("randomizer.{}.load_order.json", dungeon)),
+                        rendered,
+                    ),
+                    Err(e) => warn!(
+                        "Unable to render the {} asset load list, moved monsters may be invisible\nReason: {}",
+                        dungeon, e
+                    ),
+                }
+            }
+        }
+
+        // Missing audio is not a fatal error for the mod so just warn the user.
+        let spawning: BTreeSet<String> = spoiler
+            .dungeons
+            .values()
+            .chain(spoiler.stall_encounters.values())
+            .chain(spoiler.named_encounters.values())
+            .flatten()
+            .cloned()
+            .collect();
+        match audio::read_load_orders(&gpaths.base, &dlc_paths)
+            .map(|load_order| audio::required_banks(&load_order, &spawning, &home_dungeons))
+            .and_then(|required| audio::render(&required.load_order))
+        {
+            Ok(rendered) => plan.add_file(
+                Path::new("audio").join("randomizer.raid.load_order.json"),
+                rendered,
+            ),
+            Err(e) => warn!(
+                "Unable to read the audio load order, audio for altered spawns may be missing\nReason: {}",
+                e
+            ),
+        }
+    }
+
+    if settings.rand_curios {
+        let curio_files = rand_enemy::curio::get_data_files(&gpaths.base_dungeon)
+            .and_then(|files| rand_enemy::curio::extract_data(&files))
+            .map_err(|e| fail("Unable to read dungeon curio data", e))?;
+        // quest curios must stay in place, so curios are not randomized without the quest data
+        let quest_curios =
+            rand_enemy::curio::read_quest_curios(&gpaths.base.join("campaign").join("quest"))
+                .map_err(|e| fail("Unable to read quest data", e))?;
+        let props = rand_enemy::curio::read_props(&gpaths.base_dungeon, &curio_files);
+        spoiler.curios = rand_enemy::curio::randomize(
+            &mut plan,
+            &relative(gpaths, &gpaths.mod_dungeon),
+            curio_files,
+            &quest_curios,
+            &props,
+            seed_rng.clone(),
+            &settings.curio_options(),
+        )
+        .map_err(|e| fail("Unable to randomize curios", e))?;
+    }
+
+    if settings.randomizes_loot() {
+        let loot_files = rand_enemy::loot::get_data_files(&gpaths.base.join("loot"))
+            .and_then(|files| rand_enemy::loot::extract_data(&files))
+            .map_err(|e| fail("Unable to read loot data", e))?;
+        spoiler.loot_tables = rand_enemy::loot::randomize(
+            &mut plan,
+            Path::new("loot"),
+            loot_files,
+            seed_rng.clone(),
+            &settings.loot_options(),
+        );
+    }
+
+    // Showing the seed in game is a convenience so failures only warn.
+    if settings.show_seed_in_game {
+        match localization::read_game_tables(&gpaths.base).and_then(|strings| {
+            localization::render_seed_banner(&strings, &mod_strings(&plan), seed_val)
+        }) {
+            Ok(rendered) => plan.add_file(
+                relative(gpaths, &gpaths.mod_localization).join("rand_seed.string_table.xml"),
+                rendered,
+            ),
+            Err(e) => warn!("Unable to show the seed in game\nReason: {}", e),
+        }
+    }
+
+    // The spoiler is only for reference so failing to render it is not fatal for the mod.
+    match spoiler.render() {
+        Ok(rendered) => plan.add_file("spoiler.json", rendered),
+        Err(e) => warn!("Unable to render spoiler\nReason: {}", e),
+    }
+
+    Ok(Generated { plan, spoiler })
+}
+
+/// Replace any installed mod with the generated one and finalize it with the workshop tool
+/// Returns the race hash of the installed mod if it could be computed.
+pub fn install(gpaths: &GamePath, generated: &Generated) -> Result<Option<RaceHash>, String> {
+    helpers::uninstall_mod(&gpaths.mod_dir)?;
+    helpers::install_mod(&gpaths.mod_dir, &gpaths.mod_localization);
+    generated
+        .plan
+        .write(&gpaths.mod_dir)
+        .map_err(|e| fail("Unable to write mod data", e))?;
+    info!("Mod data written to '{}'", &gpaths.mod_dir.display());
+
+    // Hash the generated game data before the workshop tool adds its output files.
+    let race_hash = match race::compute(&gpaths.mod_dir, &generated.spoiler.seed) {
+        Ok(race_hash) => {
+            info!("Race hash: {} ({})", race_hash.phrase(), race_hash.hash);
+            let race_path = Path::join(&gpaths.mod_dir, "race.json");
+            if let Err(e) = race_hash.write_to_file(&race_path) {
+                warn!(
+                    "Unable to write race hash to file {}\n Reason: {}",
+                    &race_path.display(),
+                    e
+                );
+            }
+            Some(race_hash)
+        }
+        Err(e) => {
+            warn!("Unable to compute race hash\nReason: {}", e);
+            None
+        }
+    };
+
+    let rendered = helpers::render_project_xml(&gpaths.base, &gpaths.mod_dir)
+        .map_err(|e| fail("Unable to render project data", e))?;
+    let project_xml_path = Path::join(&gpaths.mod_dir, Path::new("project.xml"));
+    std::fs::write(&project_xml_path, rendered)
+        .map_err(|e| fail("Unable to write project.xml", e))?;
+    info!("project.xml written to '{}'", &project_xml_path.display());
+
+    helpers::run_workshop_tool(&gpaths.base, &gpaths.mod_dir)?;
+
+    Ok(race_hash)
+}
+
+/// Read the installed mod along with the spoiler describing how it was generated
+pub fn read_installed(gpaths: &GamePath) -> Result<(ModPlan, Spoiler), String> {
+    let installed =
+        ModPlan::read(&gpaths.mod_dir).map_err(|e| fail("Unable to read the installed mod", e))?;
+    let spoiler: Spoiler = installed
+        .files
+        .get(Path::new("spoiler.json"))
+        .ok_or_else(|| String::from("The installed mod has no spoiler.json"))
+        .and_then(|content| {
+            serde_json::from_slice(content)
+                .map_err(|e| fail("Unable to read the installed spoiler", e))
+        })?;
+    Ok((installed, spoiler))
+}
+
+/// Path of a mod directory relative to the mod root, used for the paths within the plan
+fn relative(gpaths: &GamePath, path: &Path) -> PathBuf {
+    path.strip_prefix(&gpaths.mod_dir)
+        .unwrap_or(path)
+        .to_path_buf()
+}
+
+/// Strings of the string tables already generated for the mod
+fn mod_strings(plan: &ModPlan) -> StringTable {
+    let mut strings = StringTable::default();
+    for (path, content) in &plan.files {
+        if path.to_string_lossy().ends_with(".string_table.xml")
+            && let Ok(table) = StringTable::parse(&String::from_utf8_lossy(content))
+        {
+            strings.merge(table);
+        }
+    }
+    strings
+}
+
+fn fail(msg: &str, e: impl std::fmt::Display) -> String {
+    error!("{}\nReason: {}", msg, e);
+    format!("{}: {}", msg, e)
+}
@@ -1,8 +1,8 @@
 use log::*;
 use regex::Regex;
 use remove_dir_all::*;
-use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::error::Error;
 use std::{
     fs,
@@ -11,25 +11,34 @@ use std::{
     thread,
 };
 
+/// DLC dungeons and the DLC directory they are shipped in
+const DLC_DUNGEONS: &[(&str, &str)] = &[
+    ("courtyard", "580100_crimson_court"),
+    ("farmstead", "735730_color_of_madness"),
+];
+
+/// How deep to search a DLC directory for its dungeon data
+pub const DLC_SEARCH_DEPTH: usize = 4;
+
 /// Collection of paths for the base game and randomizer mod
 #[derive(Debug, Clone)]
 pub struct GamePath {
     pub base: PathBuf,
     pub base_dungeon: HashMap<String, PathBuf>,
+    /// Dungeons added by DLC which can optionally join spawn randomization
+    pub dlc_dungeon: HashMap<String, PathBuf>,
+    /// Root directory of the DLC providing each DLC dungeon, holding its audio and monsters
+    pub dlc_dirs: HashMap<String, PathBuf>,
     pub base_heroes: HashMap<String, PathBuf>,
+    pub modded_heroes: HashMap<String, PathBuf>,
     pub mod_dir: PathBuf,
     pub mod_dungeon: PathBuf,
     pub mod_localization: PathBuf,
     pub mod_heroes: PathBuf,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct AudioLoadOrder {
-    pub load_order: Vec<String>,
-}
-
 /// Get all hero directories from the install path
-pub fn get_data_dirs(install_dir: &Path) -> Result<GamePath, Box<dyn Error>> {
+pub fn get_data_dirs(install_dir: &Path, app_id: u32) -> Result<GamePath, Box<dyn Error>> {
     // Early validation: check if install_dir is valid before attempting directory reads
     //let install_path = Path::new(install_dir);
     if !install_dir.exists() || !install_dir.is_dir() {
@@ -78,6 +87,31 @@ pub fn get_data_dirs(install_dir: &Path) -> Result<GamePath, Box<dyn Error>> {
         }
     }
 
+    // DLC dungeons are nested at different depths within their DLC so search for them
+    let mut dlc_dmap: HashMap<String, PathBuf> = HashMap::new();
+    let mut dlc_dirs: HashMap<String, PathBuf> = HashMap::new();
+    for (dungeon_name, dlc_name) in DLC_DUNGEONS {
+        let dlc_path = dlc_subdir.join(dlc_name);
+        let found = find_dirs(&dlc_path, dungeon_name, DLC_SEARCH_DEPTH)
+            .into_iter()
+            .find(|dir| {
+                dir.join(format!("{}.1.mash.darkest", dungeon_name))
+                    .is_file()
+            });
+        match found {
+            Some(dungeon_path) => {
+                info!(
+                    "Found DLC dungeon '{}' at {}",
+                    dungeon_name,
+                    &dungeon_path.display()
+                );
+                dlc_dmap.insert(dungeon_name.to_string(), dungeon_path);
+                dlc_dirs.insert(dungeon_name.to_string(), dlc_path);
+            }
+            None => debug!("DLC dungeon '{}' not found", dungeon_name),
+        }
+    }
+
     // use the hero name as a key for its associated data directory
     // using this the skill icons can be properly copied around later
     let mut hmap: HashMap<String, PathBuf> = HashMap::new();
@@ -137,6 +171,8 @@ pub fn get_data_dirs(install_dir: &Path) -> Result<GamePath, Box<dyn Error>> {
         }
     }
 
+    let modded_heroes = get_modded_hero_dirs(install_dir, app_id, &hmap);
+
     let randomizer_path = install_dir.join("mods").join("ddrand");
     let mod_localization_path = randomizer_path.join("localization");
     let mod_heroes_paths = randomizer_path.join("heroes");
@@ -146,7 +182,10 @@ pub fn get_data_dirs(install_dir: &Path) -> Result<GamePath, Box<dyn Error>> {
     let game_paths: GamePath = GamePath {
         base: PathBuf::from(install_dir),
         base_dungeon: dmap,
+        dlc_dungeon: dlc_dmap,
+        dlc_dirs,
         base_heroes: hmap,
+        modded_heroes,
         mod_dir: randomizer_path,
         mod_localization: mod_localization_path,
         mod_heroes: mod_heroes_paths,
@@ -158,6 +197,108 @@ pub fn get_data_dirs(install_dir: &Path) -> Result<GamePath, Box<dyn Error>> {
     Ok(game_paths)
 }
 
+/// Monster directories shipped with a DLC
+pub fn dlc_monster_dirs(dlc_path: &Path) -> Vec<PathBuf> {
+    find_dirs(dlc_path, "monsters", DLC_SEARCH_DEPTH)
+}
+
+/// Directories with the given name below a root, searching at most `depth` levels deep
+/// Results are sorted so the same directory is always picked first.
+pub fn find_dirs(root: &Path, name: &str, depth: usize) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = Vec::new();
+    let Ok(dir_read) = fs::read_dir(root) else {
+        return found;
+    };
+    for dir in dir_read.flatten() {
+        let dir_path = dir.path();
+        if !dir_path.is_dir() {
+            continue;
+        }
+        if dir.file_name() == name {
+            found.push(dir_path.clone());
+        }
+        if depth > 1 {
+            found.extend(find_dirs(&dir_path, name, depth - 1));
+        }
+    }
+    found.sort();
+    found
+}
+
+/// Find hero classes added by other mods in the game `mods` directory or the Steam workshop folder
+/// Mods replacing a base game or DLC class are ignored as they are not compatible with the randomizer.
+fn get_modded_hero_dirs(
+    install_dir: &Path,
+    app_id: u32,
+    base_heroes: &HashMap<String, PathBuf>,
+) -> HashMap<String, PathBuf> {
+    let mut mod_roots: Vec<PathBuf> = Vec::new();
+    // workshop content lives in the same Steam library as the game, `steamapps/workshop/content/<app id>`
+    let workshop_dir = install_dir
+        .parent()
+        .and_then(|p| p.parent())
+        .map(|p| p.join("workshop").join("content").join(app_id.to_string()));
+    for content_dir in [Some(install_dir.join("mods")), workshop_dir]
+        .into_iter()
+        .flatten()
+    {
+        if let Ok(dir_read) = fs::read_dir(&content_dir) {
+            for dir in dir_read.flatten() {
+                // never treat the randomizer's own output as a hero mod
+                if dir.path().is_dir() && dir.file_name() != "ddrand" {
+                    mod_roots.push(dir.path());
+                }
+            }
+        }
+    }
+    // Sort for deterministic ordering when the same class is provided by multiple mods
+    mod_roots.sort();
+
+    let mut modded: HashMap<String, PathBuf> = HashMap::new();
+    for mod_root in mod_roots {
+        let Ok(dir_read) = fs::read_dir(mod_root.join("heroes")) else {
+            continue;
+        };
+        for dir in dir_read.flatten() {
+            let dir_path = dir.path();
+            let dir_name = dir.file_name().to_string_lossy().to_string();
+            if !dir_path
+                .join(format!("{}.info.darkest", &dir_name))
+                .is_file()
+            {
+                continue;
+            }
+            if base_heroes.contains_key(&dir_name) {
+                debug!(
+                    "Ignoring modded hero '{}' at {} which replaces a base class",
+                    &dir_name,
+                    &dir_path.display()
+                );
+                continue;
+            }
+            match modded.entry(dir_name) {
+                Entry::Occupied(entry) => {
+                    debug!(
+                        "Ignoring duplicate modded hero '{}' at {}",
+                        entry.key(),
+                        &dir_path.display()
+                    );
+                }
+                Entry::Vacant(entry) => {
+                    info!(
+                        "Found modded hero '{}' at {}",
+                        entry.key(),
+                        &dir_path.display()
+                    );
+                    entry.insert(dir_path);
+                }
+            }
+        }
+    }
+
+    modded
+}
+
 pub fn install_mod(mod_dir: &Path, mod_locale_path: &Path) {
     info!("Starting randomizer mod generation");
     info!("Creating randomizer mod directory structure");
@@ -315,18 +456,6 @@ pub fn render_project_xml(install_path: &Path, mod_path: &Path) -> Result<String
     Ok(rendered)
 }
 
-/// Read the original audio load order and filter out entries which are not relevant to this mod
-pub fn get_filtered_audio_json(base_path: &Path) -> Result<String, Box<dyn Error>> {
-    let audio_json_path = base_path.join("audio").join("base.dungeon.load_order.json");
-    let mut audio_data: AudioLoadOrder =
-        serde_json::from_str(&fs::read_to_string(audio_json_path)?)?;
-    audio_data.load_order.retain(|entry| {
-        !entry.contains("props") && !entry.contains("darkestdungeon") && !entry.contains("town")
-    });
-
-    Ok(serde_json::to_string(&audio_data)?)
-}
-
 /// Convert and export localization strings to the proper game file
 pub fn run_workshop_tool(install_path: &Path, mod_path: &Path) -> Result<(), String> {
     let bin_dir = install_path.join("_windows").join("win32");
@@ -343,15 +472,29 @@ pub fn run_workshop_tool(install_path: &Path, mod_path: &Path) -> Result<(), Str
             .status()
         {
             Ok(_) => {
-                // the default output filename, which is not configurable, conflicts with the base game
-                // rename the mod localization output file to override only those values
-                // TODO: support non-English languages
+                // the default output filenames, which are not configurable, conflict with the base game
+                // rename the mod localization output files for every language to override only those values
                 let loc_path = mod_path.join("localization");
-                let from_path = loc_path.join("0_english.loc2");
-                if from_path.exists() {
-                    let to_path = loc_path.join("randomizer_english.loc2");
-                    fs::rename(from_path, to_path).unwrap();
-                } else {
+                let mut renamed = 0;
+                if let Ok(dir_read) = fs::read_dir(&loc_path) {
+                    for entry in dir_read.flatten() {
+                        let file_name = entry.file_name().to_string_lossy().to_string();
+                        let Some(lang) = file_name
+                            .strip_prefix("0_")
+                            .and_then(|f| f.strip_suffix(".loc2"))
+                        else {
+                            continue;
+                        };
+                        let to_path = loc_path.join(format!("randomizer_{}.loc2", lang));
+                        if let Err(e) = fs::rename(entry.path(), &to_path) {
+                            let msg = format!("Unable to rename {}: {}", &file_name, e);
+                            error!("{}", msg);
+                            return Err(msg);
+                        }
+                        renamed += 1;
+                    }
+                }
+                if renamed == 0 {
                     debug!("No localization data, skipping rename of non-existant file")
                 }
 
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use crate::settings::Settings;
+
+/// History file kept in the directory ddrand is run from, next to the log files
+pub const HISTORY_FILENAME: &str = "ddrand_history.json";
+
+/// A successful mod generation
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    /// UTC time of the generation in RFC 3339 format
+    pub timestamp: String,
+    pub seed: String,
+    pub settings: Settings,
+    pub version: String,
+    /// Generator algorithm version the seed was used with
+    pub generator: u32,
+    /// Steam build id of the game the mod was generated from, if known
+    pub game_build: Option<u64>,
+    pub race_hash: String,
+}
+
+impl HistoryEntry {
+    /// Short description used when listing the history
+    pub fn summary(&self) -> String {
+        let timestamp = chrono::DateTime::parse_from_rfc3339(&self.timestamp)
+            .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+            .unwrap_or_else(|_| self.timestamp.clone());
+        format!(
+            "{} | {} | v{} | {}",
+            timestamp, self.seed, self.version, self.race_hash
+        )
+    }
+}
+
+/// All recorded generations, oldest first
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// Read the history file, a missing file is an empty history
+    pub fn parse_from_file(file_path: &Path) -> Result<History, Box<dyn Error>> {
+        if !file_path.exists() {
+            return Ok(History::default());
+        }
+        let file = File::open(file_path)?;
+        let reader = BufReader::new(file);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn write_to_file(&self, file_path: &Path) -> Result<(), Box<dyn Error>> {
+        let file = File::create(file_path)?;
+        let buf_writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(buf_writer, self)?;
+        Ok(())
+    }
+
+    /// Add an entry and save the history
+    pub fn record(file_path: &Path, entry: HistoryEntry) -> Result<(), Box<dyn Error>> {
+        let mut history = History::parse_from_file(file_path)?;
+        history.add(entry);
+        history.write_to_file(file_path)
+    }
+
+    /// Add an entry as the newest, replacing any entry for the same seed and settings such as
+    /// when an entry is reinstalled
+    fn add(&mut self, entry: HistoryEntry) {
+        self.entries
+            .retain(|e| e.seed != entry.seed || e.settings != entry.settings);
+        self.entries.push(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_roundtrip() {
+        let history = History {
+            entries: vec![HistoryEntry {
+                timestamp: String::from("2026-10-18T12:30:00+00:00"),
+                seed: String::from("testseed00"),
+                settings: Settings::challenge(),
+                version: String::from("0.4.0"),
+                generator: 1,
+                game_build: Some(8559806),
+                race_hash: String::from("whip-ancestor-ancestor-ancestor-ancestor"),
+            }],
+        };
+        let json = serde_json::to_string(&history).unwrap();
+        assert_eq!(serde_json::from_str::<History>(&json).unwrap(), history);
+        assert_eq!(
+            history.entries[0].summary(),
+            "2026-10-18 12:30 UTC | testseed00 | v0.4.0 | whip-ancestor-ancestor-ancestor-ancestor"
+        );
+    }
+
+    #[test]
+    fn test_add_replaces_same_seed_and_settings() {
+        let entry = |seed: &str, settings: Settings, timestamp: &str| HistoryEntry {
+            timestamp: timestamp.to_string(),
+            seed: seed.to_string(),
+            settings,
+            version: String::from("0.4.0"),
+            generator: 1,
+            game_build: None,
+            race_hash: String::new(),
+        };
+        let mut history = History::default();
+        history.add(entry("testseed00", Settings::default(), "1"));
+        history.add(entry("testseed00", Settings::challenge(), "2"));
+        history.add(entry("testseed01", Settings::default(), "3"));
+        history.add(entry("testseed00", Settings::default(), "4"));
+
+        let timestamps: Vec<&str> = history
+            .entries
+            .iter()
+            .map(|e| e.timestamp.as_str())
+            .collect();
+        assert_eq!(timestamps, vec!["2", "3", "4"]);
+    }
+
+    #[test]
+    fn test_settings_missing_fields_use_defaults() {
+        let settings: Settings = serde_json::from_str(r#"{"rand_boss": true}"#).unwrap();
+        assert!(settings.rand_boss);
+        assert_eq!(settings.stat_variance, Settings::default().stat_variance);
+    }
+}
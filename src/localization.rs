@@ -0,0 +1,244 @@
+use log::*;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Main menu string the seed banner is added to, it is only shown outside of a campaign so the
+/// banner never covers game text while playing
+const SEED_BANNER_ID: &str = "str_main_menu_new_game";
+
+/// Game string table data, mapping each language to its entry ids and their text
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StringTable {
+    pub languages: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl StringTable {
+    /// Parse the XML of a `.string_table.xml` file
+    pub fn parse(content: &str) -> Result<StringTable, Box<dyn Error>> {
+        // some game files start with a byte order mark which the XML parser does not accept
+        let doc = roxmltree::Document::parse(content.trim_start_matches('\u{feff}'))?;
+        let mut table = StringTable::default();
+
+        for language in doc
+            .root_element()
+            .children()
+            .filter(|n| n.has_tag_name("language"))
+        {
+            let Some(lang) = language.attribute("id") else {
+                continue;
+            };
+            let entries = table.languages.entry(lang.to_string()).or_default();
+            for entry in language.children().filter(|n| n.has_tag_name("entry")) {
+                if let Some(id) = entry.attribute("id") {
+                    // text and CDATA children are both returned as text nodes
+                    let text: String = entry.children().filter_map(|n| n.text()).collect();
+                    entries.insert(id.to_string(), text);
+                }
+            }
+        }
+
+        Ok(table)
+    }
+
+    pub fn get(&self, lang: &str, id: &str) -> Option<&str> {
+        self.languages
+            .get(lang)
+            .and_then(|entries| entries.get(id))
+            .map(|text| text.as_str())
+    }
+
+    pub fn insert(&mut self, lang: &str, id: &str, text: &str) {
+        self.languages
+            .entry(lang.to_string())
+            .or_default()
+            .insert(id.to_string(), text.to_string());
+    }
+
+    /// Add all entries of another table, replacing any existing entries with the same id
+    pub fn merge(&mut self, other: StringTable) {
+        for (lang, entries) in other.languages {
+            self.languages.entry(lang).or_default().extend(entries);
+        }
+    }
+
+    /// Render the table as XML in the format used by the game
+    pub fn render(&self) -> String {
+        let mut rendered = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<root>");
+        for (lang, entries) in &self.languages {
+            rendered.push_str(&format!("\n<language id=\"{}\">", escape_attribute(lang)));
+            for (id, text) in entries {
+                rendered.push_str(&format!(
+                    "\n<entry id=\"{}\"><![CDATA[{}]]></entry>",
+                    escape_attribute(id),
+                    escape_cdata(text)
+                ));
+            }
+            rendered.push_str("\n</language>");
+        }
+        rendered.push_str("</root>");
+        rendered
+    }
+}
+
+/// Read every string table shipped in the game's `localization` directory
+pub fn read_game_tables(install_dir: &Path) -> Result<StringTable, Box<dyn Error>> {
+    let ldir = install_dir.join("localization");
+    let mut lfiles: Vec<PathBuf> = fs::read_dir(&ldir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|p| p.is_file() && p.to_string_lossy().ends_with(".string_table.xml"))
+        .collect();
+    // Sort for consistent results when the same id appears in multiple tables
+    lfiles.sort();
+
+    let mut strings = StringTable::default();
+    for file in lfiles {
+        match StringTable::parse(&fs::read_to_string(&file)?) {
+            Ok(table) => strings.merge(table),
+            Err(e) => warn!(
+                "Unable to parse string table {}\nReason: {}",
+                &file.display(),
+                e
+            ),
+        }
+    }
+
+    Ok(strings)
+}
+
+/// Build a string table adding the ddrand version and seed to the main menu so players and race
+/// referees can confirm the active mod in game.
+/// Fails when the game has no such string or another string table of the mod already changes it,
+/// as only one of the two tables would be used.
+pub fn render_seed_banner(
+    strings: &StringTable,
+    mod_strings: &StringTable,
+    seed: &str,
+) -> Result<String, Box<dyn Error>> {
+    if mod_strings
+        .languages
+        .values()
+        .any(|entries| entries.contains_key(SEED_BANNER_ID))
+    {
+        return Err(format!("'{}' is already changed by the mod", SEED_BANNER_ID).into());
+    }
+
+    let banner = format!("ddrand {} – seed {}", env!("CARGO_PKG_VERSION"), seed);
+    let mut table = StringTable::default();
+    for lang in strings.languages.keys() {
+        if let Some(text) = strings.get(lang, SEED_BANNER_ID) {
+            table.insert(lang, SEED_BANNER_ID, &format!("{} ({})", text, &banner));
+        }
+    }
+    if table.languages.is_empty() {
+        return Err(format!(
+            "'{}' was not found in the game string tables",
+            SEED_BANNER_ID
+        )
+        .into());
+    }
+    debug!("Seed banner '{}' set on '{}'", &banner, SEED_BANNER_ID);
+
+    Ok(table.render())
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A CDATA section cannot contain its own terminator, split it across two sections instead
+fn escape_cdata(text: &str) -> String {
+    text.replace("]]>", "]]]]><![CDATA[>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_string_table() {
+        let content = "\u{feff}<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<root>\n  <language id=\"english\">\n    <entry id=\"combat_skill_name_crusader_smite\"><![CDATA[Smite]]></entry>\n    <entry id=\"multi\"><![CDATA[line one\nline two]]></entry>\n  </language>\r\n  <language id=\"french\">\r\n    <entry id=\"combat_skill_name_crusader_smite\">Ch&#226;timent</entry>\r\n  </language>\n</root>";
+        let table = StringTable::parse(content).unwrap();
+
+        assert_eq!(
+            table.get("english", "combat_skill_name_crusader_smite"),
+            Some("Smite")
+        );
+        assert_eq!(table.get("english", "multi"), Some("line one\nline two"));
+        assert_eq!(
+            table.get("french", "combat_skill_name_crusader_smite"),
+            Some("Châtiment")
+        );
+        assert_eq!(table.get("german", "multi"), None);
+    }
+
+    #[test]
+    fn test_render_roundtrip_with_escaping() {
+        let mut table = StringTable::default();
+        table.insert(
+            "english",
+            "id_\"quoted\"",
+            "Text with ]]> and <tags> & more",
+        );
+        table.insert("russian", "plain", "Удар");
+
+        let rendered = table.render();
+        assert_eq!(StringTable::parse(&rendered).unwrap(), table);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut table = StringTable::default();
+        table.insert("english", "a", "first");
+        let mut other = StringTable::default();
+        other.insert("english", "a", "second");
+        other.insert("german", "b", "zweite");
+
+        table.merge(other);
+        assert_eq!(table.get("english", "a"), Some("second"));
+        assert_eq!(table.get("german", "b"), Some("zweite"));
+    }
+
+    #[test]
+    fn test_render_seed_banner() {
+        let mut game_strings = StringTable::default();
+        game_strings.insert("english", SEED_BANNER_ID, "New Game");
+        game_strings.insert("german", SEED_BANNER_ID, "Neues Spiel");
+        game_strings.insert("english", "combat_skill_name_crusader_smite", "Smite");
+
+        let rendered =
+            render_seed_banner(&game_strings, &StringTable::default(), "testseed00").unwrap();
+        let banner = StringTable::parse(&rendered).unwrap();
+        let expected = format!("ddrand {} – seed testseed00", env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            banner.get("english", SEED_BANNER_ID),
+            Some(format!("New Game ({})", expected).as_str())
+        );
+        assert_eq!(
+            banner.get("german", SEED_BANNER_ID),
+            Some(format!("Neues Spiel ({})", expected).as_str())
+        );
+        assert_eq!(
+            banner.get("english", "combat_skill_name_crusader_smite"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_render_seed_banner_missing_or_taken_id() {
+        let mut game_strings = StringTable::default();
+        game_strings.insert("english", "combat_skill_name_crusader_smite", "Smite");
+        assert!(render_seed_banner(&game_strings, &StringTable::default(), "testseed00").is_err());
+
+        game_strings.insert("english", SEED_BANNER_ID, "New Game");
+        let mut mod_strings = StringTable::default();
+        mod_strings.insert("french", SEED_BANNER_ID, "Nouvelle partie");
+        assert!(render_seed_banner(&game_strings, &mod_strings, "testseed00").is_err());
+    }
+}
@@ -11,19 +11,28 @@ use rfd::FileDialog;
 use std::collections::HashMap;
 use std::{
     env, fs,
-    io::Write,
     path::{Path, PathBuf},
 };
 
 use crate::helpers::GamePath;
-use crate::rand_hero::camping_skills;
+use crate::settings::Settings;
 
+mod audio;
 mod cli;
+mod darkest;
+mod diff;
+mod generate;
 mod helpers;
+mod history;
+mod localization;
 mod logger;
+mod plan;
+mod race;
 mod rand_enemy;
 mod rand_hero;
 mod seed;
+mod settings;
+mod spoiler;
 mod steam;
 
 const DARKEST_DUNGEON_APP_ID: u32 = 262060;
@@ -42,6 +51,10 @@ fn main() -> Result<(), slint::PlatformError> {
         std::process::exit(0);
     }
 
+    if let Some(command) = opts.command {
+        std::process::exit(run_command(command));
+    }
+
     let app_window = AppWindow::new()?;
     app_window.set_app_window_title(bin_version.into());
     app_window.set_status_text("Application started.".into());
@@ -91,13 +104,17 @@ fn main() -> Result<(), slint::PlatformError> {
 
     // Return a dictionary of paths for the various game and mod data directories.
     // Used for initial UI setup; paths are regenerated when Enable is clicked.
-    match helpers::get_data_dirs(&install_path) {
+    match helpers::get_data_dirs(&install_path, DARKEST_DUNGEON_APP_ID) {
         Ok(paths) => {
             info!("Mod directory will be: \'{}\'", paths.mod_dir.display());
             app_window.set_mod_dir(paths.mod_dir.display().to_string().into());
             // If the mod directory exist, assume the mod is installed and enable disable button.
             if paths.mod_dir.exists() && paths.mod_dir.is_dir() {
                 app_window.set_is_mod_installed(true);
+                if let Some(message) = check_installed_generator(&paths.mod_dir) {
+                    warn!("{}", message);
+                    app_window.set_status_text(message.into());
+                }
             }
             paths
         }
@@ -109,7 +126,10 @@ fn main() -> Result<(), slint::PlatformError> {
             GamePath {
                 base: PathBuf::new(),
                 base_dungeon: HashMap::new(),
+                dlc_dungeon: HashMap::new(),
+                dlc_dirs: HashMap::new(),
                 base_heroes: HashMap::new(),
+                modded_heroes: HashMap::new(),
                 mod_dir: PathBuf::new(),
                 mod_dungeon: PathBuf::new(),
                 mod_localization: PathBuf::new(),
@@ -146,7 +166,7 @@ fn main() -> Result<(), slint::PlatformError> {
 
     // Set initial placeholder seed value. When clicked the `Generate` button will replace the placeholder.
     // The user can still edit the seed if desired this just provides automatic generation if desired.
-    // Weekly seed button will allow a consistent seed based on the week number.
+    // Daily, weekly and monthly seed buttons will allow a consistent seed for everyone based on the current UTC date.
     let ui_handle = app_window.as_weak();
     ui_handle.unwrap().set_seed_value(generate_clicked().into());
 
@@ -156,10 +176,38 @@ fn main() -> Result<(), slint::PlatformError> {
         app_window.set_seed_value(generate_clicked().into());
     });
 
+    let ui_handle = app_window.as_weak();
+    app_window.on_daily_clicked(move || {
+        challenge_clicked(&ui_handle.unwrap(), seed::ChallengePeriod::Daily);
+    });
+
     let ui_handle = app_window.as_weak();
     app_window.on_weekly_clicked(move || {
-        let app_window = ui_handle.unwrap();
-        app_window.set_seed_value(weekly_clicked().into());
+        challenge_clicked(&ui_handle.unwrap(), seed::ChallengePeriod::Weekly);
+    });
+
+    let ui_handle = app_window.as_weak();
+    app_window.on_monthly_clicked(move || {
+        challenge_clicked(&ui_handle.unwrap(), seed::ChallengePeriod::Monthly);
+    });
+
+    // Keep the challenge period and countdown current, including when the seed is edited by hand.
+    let ui_handle = app_window.as_weak();
+    let challenge_timer = slint::Timer::default();
+    challenge_timer.start(
+        slint::TimerMode::Repeated,
+        std::time::Duration::from_secs(1),
+        move || update_challenge_text(&ui_handle.unwrap()),
+    );
+
+    let ui_handle = app_window.as_weak();
+    app_window.on_preview_clicked(move || {
+        preview_mod(&ui_handle.unwrap());
+    });
+
+    let ui_handle = app_window.as_weak();
+    app_window.on_compare_clicked(move || {
+        compare_installed(&ui_handle.unwrap());
     });
 
     let ui_handle = app_window.as_weak();
@@ -189,6 +237,12 @@ fn main() -> Result<(), slint::PlatformError> {
         }
     });
 
+    load_history(&app_window);
+    let ui_handle = app_window.as_weak();
+    app_window.on_history_reinstall(move |index| {
+        reinstall_history(&ui_handle.unwrap(), index);
+    });
+
     let ui_handle = app_window.as_weak();
     app_window.on_launch_game(move || {
         let handle = ui_handle.unwrap();
@@ -207,6 +261,225 @@ fn main() -> Result<(), slint::PlatformError> {
     app_window.run()
 }
 
+/// Run a command line subcommand without starting the GUI, returns the process exit code
+fn run_command(command: cli::Command) -> i32 {
+    match command {
+        cli::Command::Diff {
+            seeds,
+            settings,
+            game_dir,
+        } => run_diff(seeds, settings, game_dir),
+        cli::Command::History => print_history(),
+        cli::Command::Generate {
+            seed,
+            settings,
+            game_dir,
+            dry_run,
+        } => run_generate(seed, settings, game_dir, dry_run),
+        cli::Command::RaceHash { mod_dir } => {
+            let mod_dir = match mod_dir {
+                Some(dir) => dir,
+                None => match steam::get_darkest_dungeon_install_path(DARKEST_DUNGEON_APP_ID) {
+                    Ok(install_path) => install_path.join("mods").join("ddrand"),
+                    Err(e) => {
+                        eprintln!("ERROR: Unable to find the game installation: {}", e);
+                        return 1;
+                    }
+                },
+            };
+            if let Some(message) = check_installed_generator(&mod_dir) {
+                eprintln!("WARNING: {}", message);
+            }
+            let seed_val = fs::read_to_string(mod_dir.join("seed.txt")).unwrap_or_default();
+            match race::compute(&mod_dir, seed_val.trim()) {
+                Ok(race_hash) => {
+                    println!("Seed: {}", race_hash.seed);
+                    println!("Race hash: {} ({})", race_hash.phrase(), race_hash.hash);
+                    0
+                }
+                Err(e) => {
+                    eprintln!(
+                        "ERROR: Unable to hash mod directory {}: {}",
+                        mod_dir.display(),
+                        e
+                    );
+                    1
+                }
+            }
+        }
+    }
+}
+
+/// Options given on the command line as a JSON file in the same format as the seed history
+fn read_settings_file(settings_path: Option<PathBuf>) -> Result<Option<Settings>, String> {
+    let Some(path) = settings_path else {
+        return Ok(None);
+    };
+    fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+        .map(Some)
+        .map_err(|e| format!("Unable to read options {}: {}", path.display(), e))
+}
+
+/// Game and mod paths for command line use, autodetecting the game if no directory is given
+fn command_game_paths(game_dir: Option<PathBuf>) -> Result<GamePath, String> {
+    let game_dir = match game_dir {
+        Some(dir) => dir,
+        None => steam::get_darkest_dungeon_install_path(DARKEST_DUNGEON_APP_ID)
+            .map_err(|e| format!("Unable to find the game installation: {}", e))?,
+    };
+    helpers::get_data_dirs(&game_dir, DARKEST_DUNGEON_APP_ID)
+        .map_err(|e| format!("Invalid game directory: {}", e))
+}
+
+/// Compare two seeds, or the installed mod against a mod generated again with the same or another seed
+fn compare_mods(
+    gpaths: &GamePath,
+    seeds: &[String],
+    settings: Option<Settings>,
+) -> Result<Vec<diff::FileDiff>, String> {
+    if let [first_seed, second_seed] = seeds {
+        let settings = settings.unwrap_or_default();
+        let first = generate::build(gpaths, first_seed, &settings)?;
+        let second = generate::build(gpaths, second_seed, &settings)?;
+        return Ok(diff::compare(&first.plan, &second.plan));
+    }
+
+    let (installed, spoiler) = generate::read_installed(gpaths)?;
+    if !seed::is_compatible(spoiler.generator) {
+        return Err(format!(
+            "The installed mod was generated with incompatible generator version {}",
+            spoiler.generator
+        ));
+    }
+    let seed_val = seeds.first().unwrap_or(&spoiler.seed);
+    let settings = settings.or(spoiler.settings).unwrap_or_default();
+    let regenerated = generate::build(gpaths, seed_val, &settings)?;
+    Ok(diff::compare(&installed, &regenerated.plan))
+}
+
+fn run_diff(seeds: Vec<String>, settings_path: Option<PathBuf>, game_dir: Option<PathBuf>) -> i32 {
+    let result = read_settings_file(settings_path).and_then(|settings| {
+        command_game_paths(game_dir).and_then(|gpaths| compare_mods(&gpaths, &seeds, settings))
+    });
+    match result {
+        Ok(diffs) => {
+            print!("{}", diff::render(&diffs));
+            0
+        }
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            1
+        }
+    }
+}
+
+/// Generate the mod without the GUI, printing the results instead of installing with a dry run
+fn run_generate(
+    seed_val: Option<String>,
+    settings_path: Option<PathBuf>,
+    game_dir: Option<PathBuf>,
+    dry_run: bool,
+) -> i32 {
+    let settings = match read_settings_file(settings_path) {
+        Ok(settings) => settings.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            return 1;
+        }
+    };
+    let gpaths = match command_game_paths(game_dir) {
+        Ok(gpaths) => gpaths,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            return 1;
+        }
+    };
+    let seed_val = seed_val.unwrap_or_else(seed::generate_seed);
+
+    let generated = match generate::build(&gpaths, &seed_val, &settings) {
+        Ok(generated) => generated,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            return 1;
+        }
+    };
+    print!("{}", generated.spoiler.summary());
+    if dry_run {
+        println!("\nDry run, the installed mod was not changed");
+        return 0;
+    }
+
+    match generate::install(&gpaths, &generated) {
+        Ok(race_hash) => {
+            println!("\nddrand mod installed to {}", gpaths.mod_dir.display());
+            if let Some(race_hash) = race_hash {
+                println!("Race hash: {} ({})", race_hash.phrase(), race_hash.hash);
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            1
+        }
+    }
+}
+
+/// Print every recorded generation, oldest first
+fn print_history() -> i32 {
+    match history::History::parse_from_file(Path::new(history::HISTORY_FILENAME)) {
+        Ok(history) => {
+            if history.entries.is_empty() {
+                println!("No seed history recorded");
+            }
+            for entry in history.entries {
+                println!("{}", entry.summary());
+                if let Some(build_id) = entry.game_build {
+                    println!("    game build: {}", build_id);
+                }
+                match serde_json::to_string(&entry.settings) {
+                    Ok(options) => println!("    options: {}", options),
+                    Err(e) => eprintln!("ERROR: Unable to show options: {}", e),
+                }
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("ERROR: Unable to read seed history: {}", e);
+            1
+        }
+    }
+}
+
+/// Warn when the installed mod was generated with a different generator algorithm version
+/// The same seed would not reproduce that mod with this version of ddrand.
+/// A mod without a readable `race.json` predates generator versions and counts as version 0.
+fn check_installed_generator(mod_dir: &Path) -> Option<String> {
+    if !mod_dir.is_dir() {
+        return None;
+    }
+    let Ok(race_hash) = race::RaceHash::parse_from_file(&mod_dir.join("race.json")) else {
+        if seed::is_compatible(0) {
+            return None;
+        }
+        return Some(format!(
+            "Installed mod has no generator version recorded (generator version 0), its seed will not reproduce it with generator version {}.",
+            seed::GENERATOR_VERSION
+        ));
+    };
+    if seed::is_compatible(race_hash.generator) {
+        return None;
+    }
+    Some(format!(
+        "Installed mod was generated by ddrand {} with generator version {}, seed '{}' will not reproduce it with generator version {}.",
+        race_hash.version,
+        race_hash.generator,
+        race_hash.seed,
+        seed::GENERATOR_VERSION
+    ))
+}
+
 /// Use the Steam protocol to launch the game via Steam using the user's specified settings if any.
 /// This avoids the nees to call the Steam client directly or add complex parsing logic to find the correct binary to run.
 ///
@@ -230,15 +503,25 @@ fn launch_game() -> Result<(), std::io::Error> {
 
 fn enable_handler(handle: &AppWindow) {
     let game_dir = handle.get_game_dir().to_string();
-    match helpers::get_data_dirs(Path::new(&game_dir)) {
+    match helpers::get_data_dirs(Path::new(&game_dir), DARKEST_DUNGEON_APP_ID) {
         Ok(game_paths) => {
+            pin_challenge_ruleset(handle);
             handle.set_status_text("Starting randomization, please wait.".into());
             let handle_weak = handle.as_weak();
             slint::Timer::single_shot(std::time::Duration::from_millis(50), move || {
                 let handle = handle_weak.unwrap();
-                enable_mod(&handle, &game_paths);
-                handle.set_is_mod_installed(true);
-                handle.set_status_text("ddrand mod installed successfully.".into());
+                let completed = enable_mod(&handle, &game_paths);
+                if completed {
+                    handle.set_is_mod_installed(true);
+                    record_history(&handle);
+                    handle.set_status_text(
+                        format!(
+                            "ddrand mod installed successfully. Race hash: {}",
+                            handle.get_race_hash()
+                        )
+                        .into(),
+                    );
+                }
             });
         }
         Err(e) => {
@@ -248,289 +531,272 @@ fn enable_handler(handle: &AppWindow) {
     }
 }
 
+/// Add the generated mod to the seed history so it can be reinstalled later.
+/// The history is only for convenience so failures only warn.
+fn record_history(handle: &AppWindow) {
+    let game_build = match steam::get_game_build_id(DARKEST_DUNGEON_APP_ID) {
+        Ok(build_id) => build_id,
+        Err(e) => {
+            debug!("Unable to read game build id: {}", e);
+            None
+        }
+    };
+    let entry = history::HistoryEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        seed: handle.get_seed_value().to_string(),
+        settings: read_settings(handle),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        generator: seed::GENERATOR_VERSION,
+        game_build,
+        race_hash: handle.get_race_hash().to_string(),
+    };
+    if let Err(e) = history::History::record(Path::new(history::HISTORY_FILENAME), entry) {
+        warn!("Unable to record seed history\nReason: {}", e);
+    }
+    load_history(handle);
+}
+
+/// Show the seed history in the GUI, newest first
+fn load_history(handle: &AppWindow) {
+    let summaries: Vec<slint::SharedString> =
+        match history::History::parse_from_file(Path::new(history::HISTORY_FILENAME)) {
+            Ok(history) => history
+                .entries
+                .iter()
+                .rev()
+                .map(|entry| entry.summary().into())
+                .collect(),
+            Err(e) => {
+                warn!("Unable to read seed history\nReason: {}", e);
+                Vec::new()
+            }
+        };
+    handle.set_history_entries(std::rc::Rc::new(slint::VecModel::from(summaries)).into());
+    handle.set_history_index(0);
+}
+
+/// Restore the seed and options of a history entry, newest first, and generate that mod again
+fn reinstall_history(handle: &AppWindow, index: i32) {
+    let entry = match history::History::parse_from_file(Path::new(history::HISTORY_FILENAME)) {
+        Ok(history) => history.entries.into_iter().rev().nth(index.max(0) as usize),
+        Err(e) => {
+            warn!("Unable to read seed history\nReason: {}", e);
+            None
+        }
+    };
+    let Some(entry) = entry else {
+        handle.set_status_text("Error: Unable to read the selected history entry.".into());
+        return;
+    };
+    if !seed::is_compatible(entry.generator) {
+        handle.set_status_text(
+            format!(
+                "Error: Seed '{}' was generated by ddrand {} with an incompatible generator version.",
+                entry.seed, entry.version
+            )
+            .into(),
+        );
+        return;
+    }
+
+    info!("Reinstalling seed '{}' from history", &entry.seed);
+    handle.set_seed_value(entry.seed.into());
+    apply_settings(handle, &entry.settings);
+    update_challenge_text(handle);
+    enable_handler(handle);
+}
+
 /// Callback to generate a 32 character string to use as an input seed for the random number generator.
 fn generate_clicked() -> String {
     seed::generate_seed()
 }
 
-/// Callback to generate a 32 character string based on the year and week of the year.
-fn weekly_clicked() -> String {
-    seed::generate_weekly_seed()
+/// Callback to use the seed shared by everyone for the current challenge period.
+/// Challenges use a fixed ruleset so every player generates the same mod.
+fn challenge_clicked(handle: &AppWindow, period: seed::ChallengePeriod) {
+    let challenge = seed::generate_challenge_seed(period);
+    info!("Using challenge seed for {}", &challenge.name);
+    handle.set_seed_value(challenge.seed.into());
+    apply_settings(handle, &Settings::challenge());
+    update_challenge_text(handle);
 }
 
-fn enable_mod(handle: &AppWindow, gpaths: &GamePath) {
-    let game_dir = gpaths.base.display().to_string();
-    let mod_dir = gpaths.mod_dir.display().to_string();
-
-    // Use filesystem state as source of truth, not GUI state
-    if let Err(e) = helpers::uninstall_mod(&gpaths.mod_dir) {
-        handle.set_status_text(format!("Error: {}", e).into());
-        return;
+/// Show which challenge period the current seed belongs to and the time remaining
+fn update_challenge_text(handle: &AppWindow) {
+    let now = chrono::Utc::now();
+    let text = match seed::active_challenge(&handle.get_seed_value(), now) {
+        Some(challenge) => format!(
+            "{} challenge, {} remaining. Options are fixed for challenges.",
+            challenge.name,
+            challenge.remaining(now)
+        ),
+        None => String::new(),
+    };
+    if handle.get_challenge_text() != text.as_str() {
+        handle.set_challenge_text(text.into());
     }
+}
 
-    // Attempt to write the seed to a file in the rand_hero mod directory.
-    // If this fails just warn and continue as it is not required and is already displayed in the GUI.
-    let seed_val = handle.get_seed_value().to_string();
-    let seed_rng = seed::create_rng(&seed_val);
-    info!("Using seed: {}", &seed_val);
-
-    helpers::install_mod(&gpaths.mod_dir, &gpaths.mod_localization);
-    let seed_file_path = Path::join(&gpaths.mod_dir, "seed.txt");
-    if let Err(e) = fs::File::create(&seed_file_path)
-        .and_then(|mut seed_file| seed_file.write_all(seed_val.as_bytes()))
-    {
-        warn!(
-            "Unable to write seed to file {}\n Reason: {}",
-            &seed_file_path.to_str().unwrap(),
-            e
-        );
-    } else {
-        info!("Seed written to '{}'", &seed_file_path.display());
+/// Options may have been changed since a challenge was selected, restore its fixed ruleset
+fn pin_challenge_ruleset(handle: &AppWindow) {
+    if let Some(challenge) = seed::active_challenge(&handle.get_seed_value(), chrono::Utc::now()) {
+        info!("Applying the fixed ruleset for {}", &challenge.name);
+        apply_settings(handle, &Settings::challenge());
     }
+}
 
-    if handle.get_rand_camping_skills() {
-        let skills_file_path = &gpaths
-            .base
-            .join("raid")
-            .join("camping")
-            .join("default.camping_skills.json");
-        match camping_skills::parse_from_file(skills_file_path) {
-            Ok(skills) => {
-                info!(
-                    "Successfully read camping skill data from: {}",
-                    &skills_file_path.display().to_string()
-                );
-                let randomized_camp_skills = camping_skills::randomize(skills, seed_rng.clone());
-                match randomized_camp_skills {
-                    Ok(skill_data) => {
-                        let camp_skills_dir = &gpaths.mod_dir.join("raid").join("camping");
-                        match fs::create_dir_all(camp_skills_dir) {
-                            Ok(_) => {
-                                info!("Created directory: {}", &camp_skills_dir.display());
-                                match camping_skills::write_to_file(
-                                    &skill_data,
-                                    &camp_skills_dir.join("default.camping_skills.json"),
-                                ) {
-                                    Ok(_) => info!(
-                                        "Randomized camping skills written to: {}",
-                                        &camp_skills_dir
-                                            .join("default.camping_skills.json")
-                                            .display()
-                                    ),
-                                    Err(e) => {
-                                        error!(
-                                            "Unable to write randomized camping skills data\nReason: {}",
-                                            e
-                                        );
-                                        handle.set_status_text(
-                                            format!(
-                                                "Error: Unable to write randomized camping skills data: {}",
-                                                e
-                                            )
-                                            .into(),
-                                        );
-                                        return;
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                error!(
-                                    "Could not create directory: {}\nReason: {}",
-                                    &camp_skills_dir.display(),
-                                    e
-                                );
-                                handle.set_status_text(
-                                    format!("Error: Could not create directory: {}", e).into(),
-                                );
-                                return;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        error!("Unable to randomize camping skills data\nReason: {}", e);
-                        handle.set_status_text(
-                            format!("Error: Unable to randomize camping skills data: {}", e).into(),
-                        );
-                        return;
-                    }
-                }
-            }
-            Err(e) => {
-                error!("Unable to read camping skills data\nReason: {}", e);
-                handle.set_status_text(
-                    format!("Error: Unable to read camping skills data: {}", e).into(),
-                );
-                return;
-            }
-        }
+/// Current options selected in the GUI
+fn read_settings(handle: &AppWindow) -> Settings {
+    Settings {
+        rand_boss: handle.get_rand_boss(),
+        rand_monster: handle.get_rand_monster(),
+        rand_stall: handle.get_rand_stall(),
+        rand_named: handle.get_rand_named(),
+        rand_monster_stats: handle.get_rand_monster_stats(),
+        rand_monster_skills: handle.get_rand_monster_skills(),
+        include_courtyard: handle.get_include_courtyard(),
+        include_farmstead: handle.get_include_farmstead(),
+        rand_curios: handle.get_rand_curios(),
+        rand_curio_outcomes: handle.get_rand_curio_outcomes(),
+        rand_loot: handle.get_rand_loot(),
+        rand_camping_skills: handle.get_rand_camping_skills(),
+        rand_combat_skills: handle.get_rand_combat_skills(),
+        rand_hero_stats: handle.get_rand_hero_stats(),
+        rand_equipment: handle.get_rand_equipment(),
+        rand_modded_heroes: handle.get_rand_modded_heroes(),
+        show_seed_in_game: handle.get_show_seed_in_game(),
+        camping_max_per_class: handle.get_camping_max_per_class(),
+        camping_min_per_class: handle.get_camping_min_per_class(),
+        camping_shared_skills: handle.get_camping_shared_skills().to_string(),
+        camping_keep_owner_count: handle.get_camping_keep_owner_count(),
+        camping_shuffle_effects: handle.get_camping_shuffle_effects(),
+        camping_cost_jitter: handle.get_camping_cost_jitter(),
+        stat_variance: handle.get_stat_variance(),
+        equipment_mode: handle.get_equipment_mode().to_string(),
+        monster_variance: handle.get_monster_variance(),
+        monster_difficulty: handle.get_monster_difficulty().to_string(),
+        spawn_balance: handle.get_spawn_balance().to_string(),
+        balance_tolerance: handle.get_balance_tolerance(),
+        hard_ramp: handle.get_hard_ramp(),
+        loot_scale: handle.get_loot_scale(),
     }
+}
 
-    if handle.get_rand_combat_skills() {
-        // Attempt to create the necessary directory and return if this fails as it is required.
-        match fs::create_dir_all(&gpaths.mod_heroes) {
-            Ok(_) => debug!("Created directory: {}", &gpaths.mod_heroes.display()),
-            Err(e) => {
-                error!(
-                    "Could not create directory: {}\nReason: {}",
-                    &gpaths.mod_heroes.display(),
-                    e
-                );
-                handle.set_status_text(format!("Error: Could not create directory: {}", e).into());
-                return;
-            }
-        }
-
-        let files = rand_hero::combat_skills::get_data_files(&gpaths.base_heroes, &None).unwrap();
-        let heroes = rand_hero::combat_skills::extract_data(&files);
+/// Show the given options in the GUI
+fn apply_settings(handle: &AppWindow, settings: &Settings) {
+    handle.set_rand_boss(settings.rand_boss);
+    handle.set_rand_monster(settings.rand_monster);
+    handle.set_rand_stall(settings.rand_stall);
+    handle.set_rand_named(settings.rand_named);
+    handle.set_rand_monster_stats(settings.rand_monster_stats);
+    handle.set_rand_monster_skills(settings.rand_monster_skills);
+    handle.set_include_courtyard(settings.include_courtyard);
+    handle.set_include_farmstead(settings.include_farmstead);
+    handle.set_rand_curios(settings.rand_curios);
+    handle.set_rand_curio_outcomes(settings.rand_curio_outcomes);
+    handle.set_rand_loot(settings.rand_loot);
+    handle.set_rand_camping_skills(settings.rand_camping_skills);
+    handle.set_rand_combat_skills(settings.rand_combat_skills);
+    handle.set_rand_hero_stats(settings.rand_hero_stats);
+    handle.set_rand_equipment(settings.rand_equipment);
+    handle.set_rand_modded_heroes(settings.rand_modded_heroes);
+    handle.set_show_seed_in_game(settings.show_seed_in_game);
+    handle.set_camping_max_per_class(settings.camping_max_per_class);
+    handle.set_camping_min_per_class(settings.camping_min_per_class);
+    handle.set_camping_shared_skills(settings.camping_shared_skills.clone().into());
+    handle.set_camping_keep_owner_count(settings.camping_keep_owner_count);
+    handle.set_camping_shuffle_effects(settings.camping_shuffle_effects);
+    handle.set_camping_cost_jitter(settings.camping_cost_jitter);
+    handle.set_stat_variance(settings.stat_variance);
+    handle.set_equipment_mode(settings.equipment_mode.clone().into());
+    handle.set_monster_variance(settings.monster_variance);
+    handle.set_monster_difficulty(settings.monster_difficulty.clone().into());
+    handle.set_spawn_balance(settings.spawn_balance.clone().into());
+    handle.set_balance_tolerance(settings.balance_tolerance);
+    handle.set_hard_ramp(settings.hard_ramp);
+    handle.set_loot_scale(settings.loot_scale);
+}
 
-        let localization_map = rand_hero::combat_skills::randomize(
-            &gpaths.base_heroes,
-            &gpaths.mod_heroes,
-            heroes,
-            seed_rng.clone(),
-        );
+/// Generate and install the mod, returns whether it completed successfully.
+/// Any error is shown in the status text.
+fn enable_mod(handle: &AppWindow, gpaths: &GamePath) -> bool {
+    handle.set_race_hash("".into());
+    handle.set_preview_text("".into());
 
-        info!("Extracting localization data");
-        match rand_hero::combat_skills::extract_localizations(&gpaths.base) {
-            Ok(translation) => {
-                info!("Rendering new localization XML");
-                match rand_hero::combat_skills::render_localizations(translation, localization_map)
-                {
-                    Ok(rendered) => {
-                        let localization_filename = "rand_hero_en.string_table.xml";
-                        let localization_xml_path =
-                            Path::join(&gpaths.mod_localization, Path::new(&localization_filename));
-                        if let Err(e) = fs::File::create(&localization_xml_path).and_then(
-                            |mut localization_xml| localization_xml.write_all(rendered.as_bytes()),
-                        ) {
-                            error!(
-                                "Unable to write \'{}\'\n Reason: {}",
-                                &localization_xml_path.to_str().unwrap(),
-                                e
-                            );
-                            handle.set_status_text(
-                                format!("Error: Unable to write localization file: {}", e).into(),
-                            );
-                            return;
-                        } else {
-                            info!(
-                                "{} written to \'{}\'",
-                                &localization_filename,
-                                &localization_xml_path.display()
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        error!("Unable to render localization data\nReason: {}", e);
-                        handle.set_status_text(
-                            format!("Error: Unable to render localization data: {}", e).into(),
-                        );
-                        return;
-                    }
-                }
-            }
-            Err(e) => {
-                error!(
-                    "Unable to read default string table to build localization\nReason: {}",
-                    e
-                );
-                handle.set_status_text(
-                    format!("Error: Unable to read localization data: {}", e).into(),
-                );
-                return;
-            }
+    let seed_val = handle.get_seed_value().to_string();
+    let generated = match generate::build(gpaths, &seed_val, &read_settings(handle)) {
+        Ok(generated) => generated,
+        Err(e) => {
+            handle.set_status_text(format!("Error: {}", e).into());
+            return false;
         }
-    }
+    };
 
-    if handle.get_rand_boss() || handle.get_rand_monster() {
-        // Attempt to create the necessary directory and return if this fails as it is required.
-        match fs::create_dir_all(&gpaths.mod_dungeon) {
-            Ok(_) => debug!("Created directory: {}", &gpaths.mod_dungeon.display()),
-            Err(e) => {
-                error!(
-                    "Could not create directory: {}\nReason: {}",
-                    &gpaths.mod_dungeon.display(),
-                    e
-                );
-                handle.set_status_text(format!("Error: Could not create directory: {}", e).into());
-                return;
+    match generate::install(gpaths, &generated) {
+        Ok(race_hash) => {
+            if let Some(race_hash) = race_hash {
+                handle.set_race_hash(format!("{} ({})", race_hash.phrase(), race_hash.hash).into());
             }
+            true
         }
-        if let Ok(files) = rand_enemy::mash::get_data_files(&gpaths.base_dungeon, &None)
-            && let Ok(mashes) = rand_enemy::mash::extract_data(&files)
-        {
-            rand_enemy::mash::randomize(
-                &gpaths.mod_dungeon,
-                mashes,
-                seed_rng,
-                handle.get_rand_boss(),
-                handle.get_rand_monster(),
-            );
+        Err(e) => {
+            handle.set_status_text(format!("Error: {}", e).into());
+            false
         }
     }
+}
 
-    // Creating the directory is required for additional operations on this file.
-    // If it got created continue, otherwise just warn the user as this is not a fatal error for the mod.
-    let mod_audio_path = Path::join(&PathBuf::from(&mod_dir), "audio");
-    if fs::create_dir_all(&mod_audio_path).is_ok() {
-        if let Ok(audio_json_output) = helpers::get_filtered_audio_json(&gpaths.base) {
-            match fs::OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(Path::join(
-                    Path::new(&mod_audio_path),
-                    "randomizer.raid.load_order.json",
-                )) {
-                Ok(mut outfile) => {
-                    if outfile.write_all(audio_json_output.as_bytes()).is_ok() {
-                        info!("Audio data successfully written for randomizer mod")
-                    } else {
-                        warn!(
-                            "Unable to write mod audio data, audio for altered spawns may be missing"
-                        );
-                    }
-                }
-                Err(_) => {
-                    warn!(
-                        "Unable to create audio JSON file, audio for altered spawns may be missing"
-                    );
-                }
-            }
-        } else {
-            warn!(
-                "Unable to extract or filter audio JSON data, audio for altered spawns may be missing"
+/// Compare the installed mod against the mod the current seed and options would generate
+fn compare_installed(handle: &AppWindow) {
+    let game_dir = handle.get_game_dir().to_string();
+    let gpaths = match helpers::get_data_dirs(Path::new(&game_dir), DARKEST_DUNGEON_APP_ID) {
+        Ok(gpaths) => gpaths,
+        Err(e) => {
+            warn!("Unable to assemble game paths: {}", e);
+            handle.set_status_text("Error: Invalid game directory.".into());
+            return;
+        }
+    };
+
+    pin_challenge_ruleset(handle);
+    let seeds = vec![handle.get_seed_value().to_string()];
+    match compare_mods(&gpaths, &seeds, Some(read_settings(handle))) {
+        Ok(diffs) => {
+            handle.set_preview_text(
+                format!(
+                    "Installed mod compared to seed {}\n\n{}",
+                    &seeds[0],
+                    diff::render(&diffs)
+                )
+                .into(),
+            );
+            handle.set_status_text(
+                format!("{} files differ from the installed mod.", diffs.len()).into(),
             );
         }
-    } else {
-        warn!("Unable to write mod audio directory, audio for altered spawns may be missing");
+        Err(e) => handle.set_status_text(format!("Error: {}", e).into()),
     }
+}
 
-    match helpers::render_project_xml(&PathBuf::from(&game_dir), &PathBuf::from(&mod_dir)) {
-        Ok(rendered) => {
-            let project_xml_path = Path::join(&PathBuf::from(&mod_dir), Path::new("project.xml"));
-            if let Err(e) = fs::File::create(&project_xml_path)
-                .and_then(|mut project_xml| project_xml.write_all(rendered.as_bytes()))
-            {
-                error!(
-                    "Unable to write project.xml {}\n Reason: {}",
-                    &project_xml_path.to_str().unwrap(),
-                    e
-                );
-                handle.set_status_text(format!("Error: Unable to write project.xml: {}", e).into());
-                return;
-            } else {
-                info!("project.xml written to '{}'", &project_xml_path.display());
-            }
-        }
+/// Run the randomizers and show the results without changing the installed mod
+fn preview_mod(handle: &AppWindow) {
+    let game_dir = handle.get_game_dir().to_string();
+    let gpaths = match helpers::get_data_dirs(Path::new(&game_dir), DARKEST_DUNGEON_APP_ID) {
+        Ok(gpaths) => gpaths,
         Err(e) => {
-            error!("Unable to render project data\nReason: {}", e);
-            handle.set_status_text(format!("Error: Unable to render project data: {}", e).into());
+            warn!("Unable to assemble game paths: {}", e);
+            handle.set_status_text("Error: Invalid game directory.".into());
             return;
         }
-    }
+    };
 
-    if let Err(e) = helpers::run_workshop_tool(&PathBuf::from(game_dir), &PathBuf::from(mod_dir)) {
-        handle.set_status_text(format!("Error: {}", e).into());
+    pin_challenge_ruleset(handle);
+    let seed_val = handle.get_seed_value().to_string();
+    match generate::build(&gpaths, &seed_val, &read_settings(handle)) {
+        Ok(generated) => {
+            handle.set_preview_text(generated.spoiler.summary().into());
+            handle.set_status_text("Preview generated, the installed mod was not changed.".into());
+        }
+        Err(e) => handle.set_status_text(format!("Error: {}", e).into()),
     }
 }
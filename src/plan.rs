@@ -0,0 +1,92 @@
+use log::*;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Contents of the generated mod, kept in memory until it is installed
+/// Paths are relative to the mod directory so a mod can be previewed without touching any files.
+#[derive(Debug, Default, Clone)]
+pub struct ModPlan {
+    /// Generated file contents by destination path
+    pub files: BTreeMap<PathBuf, Vec<u8>>,
+    /// Game files copied unchanged, such as skill icons, by destination path
+    pub copies: BTreeMap<PathBuf, PathBuf>,
+}
+
+/// Files describing the mod rather than being game data, such as the seed and spoiler
+/// The loc2 files are produced by the workshop tool from the string tables.
+const METADATA_FILES: &[&str] = &[
+    "seed.txt",
+    "spoiler.json",
+    "race.json",
+    "project.xml",
+    "modfiles.txt",
+];
+
+impl ModPlan {
+    /// Read an installed mod, every file is loaded as generated content
+    pub fn read(mod_dir: &Path) -> Result<ModPlan, Box<dyn Error>> {
+        let mut plan = ModPlan::default();
+        read_dir_into(mod_dir, mod_dir, &mut plan)?;
+        Ok(plan)
+    }
+
+    pub fn add_file(&mut self, path: impl Into<PathBuf>, content: impl Into<Vec<u8>>) {
+        self.files.insert(path.into(), content.into());
+    }
+
+    pub fn add_copy(&mut self, path: impl Into<PathBuf>, from_path: impl Into<PathBuf>) {
+        self.copies.insert(path.into(), from_path.into());
+    }
+
+    /// Write every file to the mod directory
+    /// A missing copied file only warns, the same as a missing skill icon always has.
+    pub fn write(&self, mod_dir: &Path) -> Result<(), Box<dyn Error>> {
+        for (path, content) in &self.files {
+            let to_path = mod_dir.join(path);
+            if let Some(parent) = to_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&to_path, content)?;
+            debug!("Wrote {}", &to_path.display());
+        }
+
+        for (path, from_path) in &self.copies {
+            let to_path = mod_dir.join(path);
+            if let Some(parent) = to_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if let Err(e) = fs::copy(from_path, &to_path) {
+                warn!("Unable to copy {}\nReason: {}", &from_path.display(), e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Path within the mod using `/` separators on every platform
+pub fn relative_name(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whether the file describes the mod instead of being game data
+pub fn is_metadata(rel_name: &str) -> bool {
+    METADATA_FILES.contains(&rel_name) || rel_name.ends_with(".loc2")
+}
+
+fn read_dir_into(mod_dir: &Path, dir: &Path, plan: &mut ModPlan) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(dir)? {
+        let path: PathBuf = entry?.path();
+        if path.is_dir() {
+            read_dir_into(mod_dir, &path, plan)?;
+        } else {
+            plan.add_file(path.strip_prefix(mod_dir)?, fs::read(&path)?);
+        }
+    }
+    Ok(())
+}
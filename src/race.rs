@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use crate::plan::{self, ModPlan};
+use crate::seed;
+
+/// Words used to show the race hash in a form which is easy to read out and compare
+const HASH_WORDS: [&str; 64] = [
+    "ancestor", "arrow", "bandage", "banner", "bell", "blight", "blood", "bone", "brigand",
+    "candle", "cauldron", "chalice", "chain", "cove", "crow", "crown", "crypt", "curio", "dagger",
+    "dread", "eel", "estate", "fang", "field", "flame", "flask", "fungus", "ghoul", "goblet",
+    "grave", "hamlet", "hammer", "herb", "hound", "idol", "jester", "key", "lantern", "laurel",
+    "lute", "mace", "madness", "moon", "musket", "oath", "pistol", "raven", "relic", "ruin",
+    "sanctum", "scroll", "shield", "shovel", "siren", "skull", "spear", "stress", "sword", "torch",
+    "totem", "trinket", "warren", "weald", "whip",
+];
+
+/// Number of words shown for a race hash
+const WORD_COUNT: usize = 5;
+
+/// Race verification data written alongside the mod
+/// Racers compare the hash, or its words, to confirm they have generated the same mod without revealing it.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RaceHash {
+    pub seed: String,
+    pub version: String,
+    /// Generator algorithm version the seed was used with, racers need matching versions
+    pub generator: u32,
+    pub hash: String,
+    pub words: Vec<String>,
+}
+
+impl RaceHash {
+    pub fn parse_from_file(file_path: &Path) -> Result<RaceHash, Box<dyn Error>> {
+        let file = File::open(file_path)?;
+        let reader = BufReader::new(file);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn write_to_file(&self, file_path: &Path) -> Result<(), Box<dyn Error>> {
+        let file = File::create(file_path)?;
+        let buf_writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(buf_writer, self)?;
+        Ok(())
+    }
+
+    /// The words joined for display
+    pub fn phrase(&self) -> String {
+        self.words.join("-")
+    }
+}
+
+/// Compute the race hash over all generated game data in the mod directory
+pub fn compute(mod_dir: &Path, seed: &str) -> Result<RaceHash, Box<dyn Error>> {
+    let mut files: Vec<(String, Vec<u8>)> = ModPlan::read(mod_dir)?
+        .files
+        .into_iter()
+        .map(|(path, content)| (plan::relative_name(&path), content))
+        .filter(|(rel_path, _)| !plan::is_metadata(rel_path))
+        .collect();
+    let hash = hash_files(&mut files);
+
+    Ok(RaceHash {
+        seed: seed.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        generator: seed::GENERATOR_VERSION,
+        hash: format!("{:016x}", hash),
+        words: hash_words(hash),
+    })
+}
+
+/// Hash the files in path order so the result does not depend on the filesystem
+/// FNV-1a is used as it is stable across platforms and versions, unlike the standard library hasher.
+fn hash_files(files: &mut [(String, Vec<u8>)]) -> u64 {
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for (rel_path, content) in files.iter() {
+        // separate each path and content so moving bytes between them changes the hash
+        for bytes in [rel_path.as_bytes(), &[0], content, &[0]] {
+            for byte in bytes {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+    }
+    hash
+}
+
+/// Take six bits of the hash for each word
+fn hash_words(hash: u64) -> Vec<String> {
+    (0..WORD_COUNT)
+        .map(|idx| HASH_WORDS[((hash >> (idx * 6)) & 0x3f) as usize].to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_files_order_independent() {
+        let mut files = vec![
+            (String::from("heroes/a.info.darkest"), b"one".to_vec()),
+            (String::from("dungeons/b.mash.darkest"), b"two".to_vec()),
+        ];
+        let mut reversed = files.clone();
+        reversed.reverse();
+        assert_eq!(hash_files(&mut files), hash_files(&mut reversed));
+
+        let mut changed = vec![
+            (String::from("heroes/a.info.darkest"), b"one".to_vec()),
+            (String::from("dungeons/b.mash.darkest"), b"twO".to_vec()),
+        ];
+        assert_ne!(hash_files(&mut files), hash_files(&mut changed));
+    }
+
+    #[test]
+    fn test_hash_files_stable() {
+        // the value must never change between versions for the same input
+        let mut files = vec![(String::from("seed"), b"testseed00".to_vec())];
+        assert_eq!(hash_files(&mut files), 0x8971bd6df15e3507);
+        assert_eq!(hash_files(&mut []), 0xcbf29ce484222325);
+    }
+
+    #[test]
+    fn test_hash_words() {
+        let words = hash_words(0x3f);
+        assert_eq!(words.len(), WORD_COUNT);
+        assert_eq!(words[0], "whip");
+        assert_eq!(words[1], "ancestor");
+    }
+}
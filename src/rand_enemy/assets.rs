@@ -0,0 +1,286 @@
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::darkest::Entry;
+use crate::rand_enemy::{mash, monster};
+
+/// Asset load list of a dungeon, e.g. `dungeons/weald/weald.load_order.json`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LoadList {
+    pub load_order: Vec<String>,
+}
+
+/// Monsters the game can load, with a data file and the art and animations it is drawn with, and
+/// the assets each dungeon loads
+/// A monster without its data crashes the game when spawned and one without art is invisible.
+#[derive(Debug, Clone, Default)]
+pub struct AssetIndex {
+    /// Directory with the art of each monster, e.g. `monsters/pelagic_grouper`
+    monsters: BTreeMap<String, String>,
+    /// Entries of the asset load lists of each dungeon, dungeons without a list are not checked
+    load_lists: BTreeMap<String, Vec<String>>,
+}
+
+impl AssetIndex {
+    /// Index the monsters of the game's and any included DLC's `monsters` directories, and the
+    /// asset load lists next to the data of each dungeon
+    pub fn read(
+        monsters_dirs: &[PathBuf],
+        dungeon_paths: &HashMap<String, PathBuf>,
+    ) -> Result<AssetIndex, Box<dyn Error>> {
+        let mut index = AssetIndex::default();
+        for monsters_dir in monsters_dirs {
+            for info_path in monster::get_data_files(monsters_dir)? {
+                let Some(name) = info_path
+                    .file_name()
+                    .and_then(|f| f.to_str())
+                    .and_then(|f| f.strip_suffix(".info.darkest"))
+                else {
+                    continue;
+                };
+                match art_dir(monsters_dir, &info_path) {
+                    Some(art_dir) => {
+                        index.monsters.insert(name.to_string(), art_dir);
+                    }
+                    None => debug!("No animations found for monster {}", name),
+                }
+            }
+        }
+
+        for (dungeon, dungeon_path) in dungeon_paths {
+            let mut list_files: Vec<PathBuf> = fs::read_dir(dungeon_path)?
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.to_string_lossy().ends_with(".load_order.json"))
+                .collect();
+            // Sort for consistent results with the same seed, directory listings have no fixed order
+            list_files.sort();
+            for list_file in list_files {
+                let list: LoadList = serde_json::from_str(&fs::read_to_string(&list_file)?)?;
+                index
+                    .load_lists
+                    .entry(dungeon.clone())
+                    .or_default()
+                    .extend(list.load_order);
+            }
+        }
+        Ok(index)
+    }
+
+    /// Monsters of a mash entry which the game could not load
+    pub fn missing(&self, line: &str) -> Vec<String> {
+        let Some(entry) = Entry::parse(line) else {
+            return Vec::new();
+        };
+        mash::entry_monsters(&entry)
+            .into_iter()
+            .filter(|monster| !self.monsters.contains_key(monster))
+            .collect()
+    }
+
+    /// Load list entries each dungeon needs for the monsters which can spawn in its levels, keyed
+    /// as `name.id`, so monsters moved from other dungeons are drawn
+    /// The entries loading a monster's art in its home dungeon are copied, which brings along the
+    /// animations of DLC monsters as their DLC lists them, otherwise its art directory is added.
+    pub fn required_entries(
+        &self,
+        level_monsters: &BTreeMap<String, Vec<String>>,
+        home_dungeons: &BTreeMap<String, BTreeSet<String>>,
+    ) -> BTreeMap<String, Vec<String>> {
+        let mut required: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (level, monsters) in level_monsters {
+            let dungeon = level.split('.').next().unwrap_or(level);
+            let Some(load_list) = self.load_lists.get(dungeon) else {
+                continue;
+            };
+            for monster in monsters {
+                let Some(art_dir) = self.monsters.get(monster) else {
+                    continue;
+                };
+                if load_list.iter().any(|entry| loads(entry, art_dir)) {
+                    continue;
+                }
+                let mut needed: Vec<&String> = home_dungeons
+                    .get(monster)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|home| self.load_lists.get(home))
+                    .flatten()
+                    .filter(|entry| loads(entry, art_dir))
+                    .collect();
+                if needed.is_empty() {
+                    needed.push(art_dir);
+                }
+                let entries = required.entry(dungeon.to_string()).or_default();
+                for entry in needed {
+                    if !entries.contains(entry) {
+                        debug!("Adding {} to the {} load list", entry, dungeon);
+                        entries.push(entry.clone());
+                    }
+                }
+            }
+        }
+        required.retain(|_, entries| !entries.is_empty());
+        required
+    }
+
+    /// Split entries into the ones which can be placed in any dungeon and the ones which must stay
+    /// where the base game or the mod that added them put them
+    pub fn split_placeable(&self, lines: Vec<String>) -> (Vec<String>, Vec<String>) {
+        let mut placeable: Vec<String> = Vec::new();
+        let mut kept: Vec<String> = Vec::new();
+        for line in lines {
+            let missing = self.missing(&line);
+            if missing.is_empty() {
+                placeable.push(line);
+            } else {
+                warn!(
+                    "Missing data or animations for {}, keeping the spawn in its own dungeon",
+                    missing.join(", ")
+                );
+                kept.push(line);
+            }
+        }
+        (placeable, kept)
+    }
+}
+
+/// Monsters with the directory of their art
+impl FromIterator<(String, String)> for AssetIndex {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(monsters: I) -> AssetIndex {
+        AssetIndex {
+            monsters: monsters.into_iter().collect(),
+            load_lists: BTreeMap::new(),
+        }
+    }
+}
+
+pub fn render(load_order: &[String]) -> Result<String, Box<dyn Error>> {
+    Ok(serde_json::to_string(&LoadList {
+        load_order: load_order.to_vec(),
+    })?)
+}
+
+/// Directory with an `anim` directory next to the monster's data file or in one of its parent
+/// directories within the `monsters` directory, variants of a monster share the same animations
+/// The directory is relative to the game data, e.g. `monsters/pelagic_grouper`.
+fn art_dir(monsters_dir: &Path, info_path: &Path) -> Option<String> {
+    let dir = info_path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(monsters_dir))
+        .find(|dir| dir.join("anim").is_dir())?;
+    let mut art_dir = String::from("monsters");
+    for component in dir.strip_prefix(monsters_dir).ok()?.components() {
+        art_dir.push('/');
+        art_dir.push_str(&component.as_os_str().to_string_lossy());
+    }
+    Some(art_dir)
+}
+
+/// Whether a load list entry loads the art directory, either the directory itself, one of its
+/// parents or a file within it
+fn loads(entry: &str, art_dir: &str) -> bool {
+    let entry = entry
+        .replace('\\', "/")
+        .trim_end_matches('/')
+        .to_lowercase();
+    let art_dir = art_dir.to_lowercase();
+    let is_within = |path: &str, dir: &str| {
+        path.strip_prefix(dir)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    };
+    is_within(&art_dir, &entry) || is_within(&entry, &art_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_placeable() {
+        let index: AssetIndex = [("bone_soldier_A", "bone_soldier"), ("ghoul_A", "ghoul")]
+            .into_iter()
+            .map(|(monster, dir)| (monster.to_string(), format!("monsters/{}", dir)))
+            .collect();
+        let lines = vec![
+            String::from("hall: .chance 2 .types bone_soldier_A bone_soldier_A"),
+            String::from("room: .chance 1 .types ghoul_A modded_monster_A"),
+            String::from("hall: 3 ghoul_A"),
+        ];
+        assert_eq!(index.missing(&lines[1]), vec!["modded_monster_A"]);
+
+        let (placeable, kept) = index.split_placeable(lines.clone());
+        assert_eq!(placeable, vec![lines[0].clone(), lines[2].clone()]);
+        assert_eq!(kept, vec![lines[1].clone()]);
+    }
+
+    #[test]
+    fn test_required_entries() {
+        let mut index: AssetIndex = [
+            ("pelagic_grouper_A", "pelagic_grouper"),
+            ("ghoul_A", "ghoul"),
+            ("bone_soldier_A", "bone_soldier"),
+            ("courtyard_bloodsucker_A", "bloodsucker"),
+        ]
+        .into_iter()
+        .map(|(monster, dir)| (monster.to_string(), format!("monsters/{}", dir)))
+        .collect();
+        index.load_lists.insert(
+            String::from("weald"),
+            vec![
+                String::from("monsters\\ghoul"),
+                String::from("monsters/bone_soldier/anim/bone_soldier.sprite.idle.atlas"),
+                String::from("monsters/pelagic"),
+            ],
+        );
+        index.load_lists.insert(
+            String::from("courtyard"),
+            vec![
+                String::from("monsters/bloodsucker/anim"),
+                String::from("monsters/bloodsucker/fx"),
+                String::from("monsters/crimson_fountain"),
+            ],
+        );
+        let home_dungeons = BTreeMap::from([(
+            String::from("courtyard_bloodsucker_A"),
+            BTreeSet::from([String::from("courtyard")]),
+        )]);
+        let level_monsters: BTreeMap<String, Vec<String>> = [
+            (
+                "weald.1",
+                vec!["ghoul_A", "pelagic_grouper_A", "bone_soldier_A"],
+            ),
+            ("weald.3", vec!["pelagic_grouper_A", "modded_A"]),
+            ("crypts.1", vec!["pelagic_grouper_A"]),
+            ("weald.5", vec!["courtyard_bloodsucker_A"]),
+        ]
+        .into_iter()
+        .map(|(level, monsters)| {
+            (
+                level.to_string(),
+                monsters.into_iter().map(|m| m.to_string()).collect(),
+            )
+        })
+        .collect();
+
+        // crypts has no load list so nothing is checked there, the DLC monster brings the
+        // entries of its home dungeon
+        let required = index.required_entries(&level_monsters, &home_dungeons);
+        assert_eq!(
+            required,
+            BTreeMap::from([(
+                String::from("weald"),
+                vec![
+                    String::from("monsters/pelagic_grouper"),
+                    String::from("monsters/bloodsucker/anim"),
+                    String::from("monsters/bloodsucker/fx"),
+                ]
+            )])
+        );
+    }
+}
@@ -0,0 +1,444 @@
+use log::*;
+use rand::Rng;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::darkest::Entry;
+use crate::helpers;
+use crate::plan::ModPlan;
+use crate::seed::SeedRng;
+
+/// A dungeon data file with `curio` entries, all other lines are kept byte for byte
+#[derive(Debug, Clone)]
+pub struct CurioFile {
+    dungeon: String,
+    /// File name within the dungeon directory
+    name: String,
+    lines: Vec<String>,
+}
+
+impl CurioFile {
+    /// Data file of a dungeon from the file's content
+    pub fn new(dungeon: &str, name: &str, content: &str) -> CurioFile {
+        CurioFile {
+            dungeon: dungeon.to_string(),
+            name: name.to_string(),
+            lines: content.lines().map(|l| l.to_string()).collect(),
+        }
+    }
+}
+
+/// Files of the props a curio is drawn with in a dungeon, by dungeon and curio id
+/// Each file is listed with its path within the dungeon directory and its path in the game.
+pub type CurioProps = BTreeMap<(String, String), Vec<(PathBuf, PathBuf)>>;
+
+/// Options for curio randomization
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CurioOptions {
+    /// Also trade the item interactions and outcomes between the shuffled curios
+    pub shuffle_outcomes: bool,
+}
+
+/// Curio properties which decide where and how often it appears, everything else is its item
+/// interactions and outcomes
+const PLACEMENT_PROPERTIES: &[&str] = &["id", "chance"];
+
+/// Helper function to get the data files with curios of every dungeon, spawn files are left to `mash`
+pub fn get_data_files(
+    dungeon_paths: &HashMap<String, PathBuf>,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut curio_files: Vec<PathBuf> = Vec::new();
+    for dungeon_path in dungeon_paths.values() {
+        for entry in fs::read_dir(dungeon_path)? {
+            let path: PathBuf = entry?.path();
+            let fname = path.to_string_lossy();
+            if path.is_file() && fname.ends_with(".darkest") && !fname.ends_with(".mash.darkest") {
+                curio_files.push(path);
+            }
+        }
+    }
+
+    // Sort for consistent results with the same seed, HashMap iteration order is randomized
+    curio_files.sort();
+
+    Ok(curio_files)
+}
+
+/// Read the data files, files without any curio are skipped
+pub fn extract_data(datafiles: &[PathBuf]) -> Result<Vec<CurioFile>, Box<dyn Error>> {
+    let mut curio_files: Vec<CurioFile> = Vec::new();
+    for cpath in datafiles {
+        let name = cpath
+            .file_name()
+            .and_then(|f| f.to_str())
+            .ok_or_else(|| format!("Invalid curio file name: {}", cpath.display()))?;
+        let dungeon = cpath
+            .parent()
+            .and_then(|d| d.file_name())
+            .and_then(|d| d.to_str())
+            .ok_or_else(|| format!("Invalid dungeon directory: {}", cpath.display()))?;
+        let file = CurioFile::new(dungeon, name, &fs::read_to_string(cpath)?);
+        if file.lines.iter().any(|line| curio_entry(line).is_some()) {
+            curio_files.push(file);
+        }
+    }
+
+    Ok(curio_files)
+}
+
+/// Curios named by the quest definitions of the game's `campaign/quest` directory, any string of a
+/// property with curio in its name is taken as a curio id
+pub fn read_quest_curios(quest_dir: &Path) -> Result<BTreeSet<String>, Box<dyn Error>> {
+    let mut quest_files: Vec<PathBuf> = fs::read_dir(quest_dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.to_string_lossy().ends_with(".json"))
+        .collect();
+    quest_files.sort();
+
+    let mut quest_curios: BTreeSet<String> = BTreeSet::new();
+    for quest_file in quest_files {
+        let quest_data: Value = serde_json::from_str(&fs::read_to_string(&quest_file)?)?;
+        collect_curios(&quest_data, false, &mut quest_curios);
+    }
+    debug!("Quest curios: {:?}", &quest_curios);
+
+    Ok(quest_curios)
+}
+
+fn collect_curios(value: &Value, is_curio: bool, curios: &mut BTreeSet<String>) {
+    match value {
+        Value::String(id) if is_curio => {
+            curios.insert(id.clone());
+        }
+        Value::Array(values) => {
+            for value in values {
+                collect_curios(value, is_curio, curios);
+            }
+        }
+        Value::Object(properties) => {
+            for (name, value) in properties {
+                collect_curios(value, name.contains("curio"), curios);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Prop files of the curios of each dungeon, found in a directory named after the curio within the
+/// dungeon directory
+pub fn read_props(
+    dungeon_paths: &HashMap<String, PathBuf>,
+    curio_files: &[CurioFile],
+) -> CurioProps {
+    let mut props = CurioProps::new();
+    for file in curio_files {
+        let Some(dungeon_path) = dungeon_paths.get(&file.dungeon) else {
+            continue;
+        };
+        for id in file
+            .lines
+            .iter()
+            .filter_map(|line| curio_entry(line))
+            .map(|e| curio_id(&e))
+        {
+            let key = (file.dungeon.clone(), id);
+            if props.contains_key(&key) {
+                continue;
+            }
+            let Some(prop_dir) =
+                helpers::find_dirs(dungeon_path, &key.1, helpers::DLC_SEARCH_DEPTH)
+                    .into_iter()
+                    .next()
+            else {
+                continue;
+            };
+            let mut prop_files: Vec<(PathBuf, PathBuf)> = Vec::new();
+            list_files(dungeon_path, &prop_dir, &mut prop_files);
+            prop_files.sort();
+            props.insert(key, prop_files);
+        }
+    }
+    props
+}
+
+fn list_files(dungeon_path: &Path, dir: &Path, files: &mut Vec<(PathBuf, PathBuf)>) {
+    let Ok(dir_read) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in dir_read.flatten().map(|entry| entry.path()) {
+        if path.is_dir() {
+            list_files(dungeon_path, &path, files);
+        } else if let Ok(rel_path) = path.strip_prefix(dungeon_path) {
+            files.push((rel_path.to_path_buf(), path.clone()));
+        }
+    }
+}
+
+/// Shuffle which curios appear in which dungeon, each curio line is moved whole so its weight is kept
+/// Quest curios never move or trade outcomes as quests rely on finding them in their dungeon.
+/// Curios moved to another dungeon bring the props they are drawn with in their own dungeon.
+/// Returns the curios which can appear in each dungeon.
+pub fn randomize(
+    plan: &mut ModPlan,
+    mod_dpath: &Path,
+    curio_files: Vec<CurioFile>,
+    quest_curios: &BTreeSet<String>,
+    props: &CurioProps,
+    rng: SeedRng,
+    options: &CurioOptions,
+) -> Result<BTreeMap<String, Vec<String>>, Box<dyn Error>> {
+    info!("Randomizing curios");
+    let mut seed_rng: SeedRng = rng;
+    let mut curio_files = curio_files;
+
+    // every line which can be shuffled, as its file and line index
+    let slots: Vec<(usize, usize)> = curio_files
+        .iter()
+        .enumerate()
+        .flat_map(|(fidx, file)| {
+            file.lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| {
+                    curio_entry(line).is_some_and(|entry| !quest_curios.contains(&curio_id(&entry)))
+                })
+                .map(move |(lidx, _)| (fidx, lidx))
+        })
+        .collect();
+    let original: Vec<String> = slots
+        .iter()
+        .map(|(fidx, lidx)| curio_files[*fidx].lines[*lidx].clone())
+        .collect();
+
+    let mut pool = original.clone();
+    let mut placed: Vec<String> = Vec::new();
+    while !pool.is_empty() {
+        let rand_idx = seed_rng.random_range(0..pool.len());
+        placed.push(pool.remove(rand_idx));
+    }
+
+    if options.shuffle_outcomes {
+        let mut outcomes: Vec<Entry> = placed.iter().filter_map(|line| curio_entry(line)).collect();
+        for line in placed.iter_mut() {
+            let Some(entry) = curio_entry(line) else {
+                continue;
+            };
+            let rand_idx = seed_rng.random_range(0..outcomes.len());
+            *line = trade_outcomes(&entry, &outcomes.remove(rand_idx)).to_string();
+        }
+    }
+    verify_curios(&original, &placed)?;
+
+    // the dungeon each curio came from, to find its props
+    let mut origins: BTreeMap<String, String> = BTreeMap::new();
+    for (fidx, lidx) in &slots {
+        let file = &curio_files[*fidx];
+        if let Some(entry) = curio_entry(&file.lines[*lidx]) {
+            origins
+                .entry(curio_id(&entry))
+                .or_insert_with(|| file.dungeon.clone());
+        }
+    }
+
+    for ((fidx, lidx), line) in slots.into_iter().zip(placed) {
+        let dungeon = curio_files[fidx].dungeon.clone();
+        if let Some(id) = curio_entry(&line).map(|e| curio_id(&e))
+            && let Some(origin) = origins.get(&id)
+            && *origin != dungeon
+            && !props.contains_key(&(dungeon.clone(), id.clone()))
+        {
+            for (rel_path, from_path) in props
+                .get(&(origin.clone(), id.clone()))
+                .into_iter()
+                .flatten()
+            {
+                plan.add_copy(mod_dpath.join(&dungeon).join(rel_path), from_path);
+            }
+        }
+        curio_files[fidx].lines[lidx] = line;
+    }
+
+    let mut curios: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for file in &curio_files {
+        let mut content = String::new();
+        for line in &file.lines {
+            content.push_str(&format!("{}\n", line));
+        }
+        plan.add_file(mod_dpath.join(&file.dungeon).join(&file.name), content);
+
+        let dungeon_curios = curios.entry(file.dungeon.clone()).or_default();
+        for id in file
+            .lines
+            .iter()
+            .filter_map(|line| curio_entry(line))
+            .map(|e| curio_id(&e))
+        {
+            if !dungeon_curios.contains(&id) {
+                dungeon_curios.push(id);
+            }
+        }
+        dungeon_curios.sort();
+    }
+
+    Ok(curios)
+}
+
+/// A `curio` entry with an id, any other line is not a curio
+fn curio_entry(line: &str) -> Option<Entry> {
+    Entry::parse(line).filter(|entry| entry.key == "curio" && entry.get("id").is_some())
+}
+
+fn curio_id(entry: &Entry) -> String {
+    entry
+        .get("id")
+        .and_then(|values| values.first())
+        .map(|id| id.trim_matches('"').to_string())
+        .unwrap_or_default()
+}
+
+/// Curio line with the placement of the original curio and the interactions and outcomes of the traded one
+fn trade_outcomes(original: &Entry, traded: &Entry) -> Entry {
+    let mut props: Vec<(String, Vec<String>)> = original
+        .props
+        .iter()
+        .filter(|(name, _)| PLACEMENT_PROPERTIES.contains(&name.as_str()))
+        .cloned()
+        .collect();
+    props.extend(
+        traded
+            .props
+            .iter()
+            .filter(|(name, _)| !PLACEMENT_PROPERTIES.contains(&name.as_str()))
+            .cloned(),
+    );
+
+    Entry {
+        key: original.key.clone(),
+        values: original.values.clone(),
+        props,
+    }
+}
+
+/// Check every shuffled curio is still placed exactly once
+fn verify_curios(original: &[String], placed: &[String]) -> Result<(), Box<dyn Error>> {
+    let count_ids = |lines: &[String]| {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for entry in lines.iter().filter_map(|line| curio_entry(line)) {
+            *counts.entry(curio_id(&entry)).or_default() += 1;
+        }
+        counts
+    };
+    if count_ids(original) != count_ids(placed) {
+        return Err("Curio entries were lost or duplicated".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed;
+
+    #[test]
+    fn test_randomize_keeps_quest_curios() {
+        let files = vec![
+            CurioFile::new(
+                "crypts",
+                "crypts.dungeon.darkest",
+                r#"// curios
+curio: .id "sarcophagus" .chance 3 .items shovel .outcome loot
+curio: .id "sconce" .chance 1 .outcome quest"#,
+            ),
+            CurioFile::new(
+                "weald",
+                "weald.dungeon.darkest",
+                r#"// curios
+curio: .id "mushrooms" .chance 2 .items herbs .outcome purge
+curio: .id "mildred_locket" .chance 1 .outcome quest"#,
+            ),
+        ];
+        let quest_curios = BTreeSet::from([String::from("sconce"), String::from("mildred_locket")]);
+        let props = CurioProps::from([(
+            (String::from("crypts"), String::from("sarcophagus")),
+            vec![(
+                PathBuf::from("props/sarcophagus/sarcophagus.png"),
+                PathBuf::from("game/dungeons/crypts/props/sarcophagus/sarcophagus.png"),
+            )],
+        )]);
+        let mut plan = ModPlan::default();
+        let curios = randomize(
+            &mut plan,
+            Path::new("dungeons"),
+            files,
+            &quest_curios,
+            &props,
+            seed::create_rng("testseed00"),
+            &CurioOptions {
+                shuffle_outcomes: true,
+            },
+        )
+        .unwrap();
+
+        assert!(curios["crypts"].contains(&String::from("sconce")));
+        assert!(curios["weald"].contains(&String::from("mildred_locket")));
+        let mut shuffled: Vec<String> = curios.clone().into_values().flatten().collect();
+        shuffled.sort();
+        assert_eq!(
+            shuffled,
+            vec!["mildred_locket", "mushrooms", "sarcophagus", "sconce"]
+        );
+
+        let crypts = String::from_utf8_lossy(
+            &plan.files[Path::new("dungeons/crypts/crypts.dungeon.darkest")],
+        )
+        .to_string();
+        assert!(crypts.starts_with("// curios\n"));
+        assert!(crypts.contains(r#"curio: .id "sconce" .chance 1 .outcome quest"#));
+
+        // the sarcophagus brings its props only when it was moved to the weald
+        let moved = curios["weald"].contains(&String::from("sarcophagus"));
+        assert_eq!(
+            plan.copies.contains_key(Path::new(
+                "dungeons/weald/props/sarcophagus/sarcophagus.png"
+            )),
+            moved
+        );
+        assert_eq!(plan.copies.len(), usize::from(moved));
+    }
+
+    #[test]
+    fn test_collect_curios() {
+        let quest_data: Value = serde_json::from_str(
+            r#"{"goals": [
+                {"id": "activate_sconces", "type": "activate_inventory",
+                 "data": {"curio_names": ["sconce"], "amount": 3}},
+                {"id": "gather_lockets", "data": {"item": "mildred_locket"}},
+                {"id": "find_altar", "data": {"curio": "altar_of_light"}}
+            ]}"#,
+        )
+        .unwrap();
+        let mut curios: BTreeSet<String> = BTreeSet::new();
+        collect_curios(&quest_data, false, &mut curios);
+        assert_eq!(
+            curios,
+            BTreeSet::from([String::from("altar_of_light"), String::from("sconce")])
+        );
+    }
+
+    #[test]
+    fn test_trade_outcomes() {
+        let original =
+            curio_entry(r#"curio: .id "sarcophagus" .chance 3 .items shovel .outcome loot"#)
+                .unwrap();
+        let traded =
+            curio_entry(r#"curio: .id "mushrooms" .chance 2 .items herbs .outcome purge"#).unwrap();
+        assert_eq!(
+            trade_outcomes(&original, &traded).to_string(),
+            r#"curio: .id "sarcophagus" .chance 3 .items herbs .outcome purge"#
+        );
+    }
+}
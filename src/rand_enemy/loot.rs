@@ -0,0 +1,347 @@
+use log::*;
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::darkest::{Entry, Number};
+use crate::plan::ModPlan;
+use crate::seed::SeedRng;
+
+/// A `.darkest` file of the game's `loot` directory
+/// Every drop is its own line naming the table it belongs to, e.g.
+/// `loot: .id "CRYPTS_ENEMY" .difficulty 1 .dungeon "crypts" .chances 3 .type "gold" .amount 250`,
+/// any other line is kept as is.
+#[derive(Debug, Clone)]
+pub struct LootFile {
+    /// File name within the `loot` directory
+    name: String,
+    lines: Vec<String>,
+}
+
+impl LootFile {
+    /// Loot file from its name and content
+    pub fn new(name: &str, content: &str) -> LootFile {
+        LootFile {
+            name: name.to_string(),
+            lines: content.lines().map(|l| l.to_string()).collect(),
+        }
+    }
+}
+
+/// Table of a drop line, drops of the same table share its id, difficulty and dungeon
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct TableKey {
+    id: String,
+    difficulty: String,
+    dungeon: String,
+}
+
+/// Options for loot table randomization
+#[derive(Debug, Clone, Copy)]
+pub struct LootOptions {
+    /// Shuffle the drops between tables of the same dungeon and difficulty, so battles, curios and other
+    /// sources trade their gold, heirlooms, gems and trinkets
+    pub shuffle: bool,
+    /// Payout of every drop as a percentage of the base game amount
+    pub scale: u32,
+}
+
+/// Helper function to get every loot file
+pub fn get_data_files(loot_dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut loot_files: Vec<PathBuf> = Vec::new();
+    for entry in fs::read_dir(loot_dir)? {
+        let path: PathBuf = entry?.path();
+        if path.is_file() && path.to_string_lossy().ends_with(".darkest") {
+            loot_files.push(path);
+        }
+    }
+
+    // Sort for consistent results with the same seed, directory listings have no fixed order
+    loot_files.sort();
+
+    Ok(loot_files)
+}
+
+pub fn extract_data(datafiles: &[PathBuf]) -> Result<Vec<LootFile>, Box<dyn Error>> {
+    let mut loot_files: Vec<LootFile> = Vec::new();
+    for lpath in datafiles {
+        let name = lpath
+            .file_name()
+            .and_then(|f| f.to_str())
+            .ok_or_else(|| format!("Invalid loot file name: {}", lpath.display()))?;
+        loot_files.push(LootFile::new(name, &fs::read_to_string(lpath)?));
+    }
+
+    Ok(loot_files)
+}
+
+/// Shuffle and scale the loot tables, returning the table each table's drops came from
+/// Tables are keyed by their file and id, along with their difficulty, e.g.
+/// `crypts.loot.darkest/CRYPTS_ENEMY.1`.
+pub fn randomize(
+    plan: &mut ModPlan,
+    mod_lpath: &Path,
+    loot_files: Vec<LootFile>,
+    rng: SeedRng,
+    options: &LootOptions,
+) -> BTreeMap<String, Vec<String>> {
+    info!("Randomizing loot tables");
+    let mut seed_rng: SeedRng = rng;
+    let mut loot_files = loot_files;
+    let mut sources: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    if options.shuffle {
+        // tables of each dungeon and difficulty as their file and key, with the drop lines of
+        // each table, using sorted maps for consistent output with the same seed
+        let mut groups: BTreeMap<(String, String), Vec<(usize, TableKey)>> = BTreeMap::new();
+        let mut drops: BTreeMap<(usize, TableKey), Vec<String>> = BTreeMap::new();
+        for (fidx, file) in loot_files.iter().enumerate() {
+            for line in &file.lines {
+                let Some(key) = table_key(line, &file.name) else {
+                    continue;
+                };
+                let table_drops = drops.entry((fidx, key.clone())).or_default();
+                if table_drops.is_empty() {
+                    groups
+                        .entry((key.dungeon.clone(), key.difficulty.clone()))
+                        .or_default()
+                        .push((fidx, key));
+                }
+                table_drops.push(line.clone());
+            }
+        }
+
+        let mut placed: BTreeMap<(usize, TableKey), Vec<String>> = BTreeMap::new();
+        for ((dungeon, difficulty), tables) in groups {
+            debug!(
+                "Shuffling {} loot tables of {} difficulty {}",
+                tables.len(),
+                &dungeon,
+                &difficulty
+            );
+            let mut pool: Vec<&(usize, TableKey)> = tables.iter().collect();
+            for (fidx, key) in &tables {
+                let rand_idx = seed_rng.random_range(0..pool.len());
+                let source = pool.remove(rand_idx);
+                let moved: Vec<String> = drops[source]
+                    .iter()
+                    .map(|line| retable(line, key))
+                    .collect();
+                sources.insert(
+                    table_name(&loot_files[*fidx].name, key),
+                    vec![table_name(&loot_files[source.0].name, &source.1)],
+                );
+                placed.insert((*fidx, key.clone()), moved);
+            }
+        }
+
+        // each table's new drops take the place of its first drop line
+        for (fidx, file) in loot_files.iter_mut().enumerate() {
+            let mut lines: Vec<String> = Vec::new();
+            for line in &file.lines {
+                match table_key(line, &file.name) {
+                    Some(key) => {
+                        if let Some(table_drops) = placed.remove(&(fidx, key)) {
+                            lines.extend(table_drops);
+                        }
+                    }
+                    None => lines.push(line.clone()),
+                }
+            }
+            file.lines = lines;
+        }
+    }
+
+    for file in loot_files.iter_mut() {
+        let mut content = String::new();
+        for line in file.lines.iter_mut() {
+            scale_payout(line, options.scale);
+            content.push_str(&format!("{}\n", line));
+        }
+        plan.add_file(mod_lpath.join(&file.name), content);
+    }
+
+    sources
+}
+
+/// Table of a drop line, `None` for lines which are not drops
+/// Drops without a `.dungeon` belong to the dungeon the file is named after.
+fn table_key(line: &str, file_name: &str) -> Option<TableKey> {
+    let entry = Entry::parse(line)?;
+    let first = |name: &str| {
+        entry
+            .get(name)
+            .and_then(|values| values.first())
+            .map(|value| value.trim_matches('"').to_string())
+    };
+    Some(TableKey {
+        id: first("id")?,
+        difficulty: first("difficulty").unwrap_or_default(),
+        dungeon: first("dungeon")
+            .unwrap_or_else(|| file_name.split('.').next().unwrap_or_default().to_string()),
+    })
+}
+
+/// Table name for the spoiler, the same id is used in several files and difficulties
+fn table_name(file_name: &str, key: &TableKey) -> String {
+    if key.difficulty.is_empty() {
+        format!("{}/{}", file_name, key.id)
+    } else {
+        format!("{}/{}.{}", file_name, key.id, key.difficulty)
+    }
+}
+
+/// Drop line moved to another table, only the table id is changed as tables are only shuffled
+/// within the same dungeon and difficulty
+fn retable(line: &str, key: &TableKey) -> String {
+    let Some(mut entry) = Entry::parse(line) else {
+        return line.to_string();
+    };
+    if let Some(id) = entry.get_mut("id").and_then(|values| values.first_mut()) {
+        *id = format!("\"{}\"", key.id);
+    }
+    entry.to_string()
+}
+
+/// Scale the `.amount` of a drop, whole numbers stay whole and a drop is never scaled away entirely
+fn scale_payout(line: &mut String, scale: u32) {
+    if scale == 100 {
+        return;
+    }
+    let Some(mut entry) = Entry::parse(line) else {
+        return;
+    };
+    let Some(values) = entry.get_mut("amount") else {
+        return;
+    };
+    let factor = f64::from(scale) / 100.0;
+    for value in values.iter_mut() {
+        let Some(mut number) = Number::parse(value) else {
+            continue;
+        };
+        if number.value <= 0.0 {
+            continue;
+        }
+        let scaled = number.value * factor;
+        number.value = if number.value.fract() == 0.0 {
+            scaled.round().max(1.0)
+        } else {
+            (scaled * 100.0).round() / 100.0
+        };
+        *value = number.to_string();
+    }
+    *line = entry.to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed;
+
+    const TEST_LOOT: &str = r#"// crypts loot
+loot: .id "CRYPTS_ENEMY" .difficulty 1 .dungeon "crypts" .chances 3 .type "gold" .amount 250
+loot: .id "CRYPTS_ENEMY" .difficulty 1 .dungeon "crypts" .chances 1 .type "heirloom" .amount 2
+loot: .id "CRYPTS_CURIO" .difficulty 1 .dungeon "crypts" .chances 1 .type "trinket" .amount 1
+loot: .id "CRYPTS_ENEMY" .difficulty 3 .dungeon "crypts" .chances 1 .type "gem" .amount 0.5
+loot: .id "WEALD_ENEMY" .difficulty 1 .dungeon "weald" .chances 2 .type "gold" .amount 300
+"#;
+
+    #[test]
+    fn test_table_key() {
+        let key = table_key(TEST_LOOT.lines().nth(1).unwrap(), "base.loot.darkest").unwrap();
+        assert_eq!(
+            key,
+            TableKey {
+                id: String::from("CRYPTS_ENEMY"),
+                difficulty: String::from("1"),
+                dungeon: String::from("crypts"),
+            }
+        );
+        assert_eq!(
+            table_key(r#"loot: .id "SHAMBLER" .chances 1"#, "weald.loot.darkest")
+                .unwrap()
+                .dungeon,
+            "weald"
+        );
+        assert!(table_key("// crypts loot", "base.loot.darkest").is_none());
+    }
+
+    #[test]
+    fn test_randomize_shuffles_within_dungeon_and_difficulty() {
+        let mut plan = ModPlan::default();
+        let sources = randomize(
+            &mut plan,
+            Path::new("loot"),
+            vec![
+                LootFile::new("base.loot.darkest", TEST_LOOT),
+                LootFile::new("dlc.loot.darkest", TEST_LOOT),
+            ],
+            seed::create_rng("testseed00"),
+            &LootOptions {
+                shuffle: true,
+                scale: 200,
+            },
+        );
+
+        // the same ids in both files are separate tables
+        assert_eq!(sources.len(), 8);
+        let crypts: Vec<&str> = ["CRYPTS_ENEMY.1", "CRYPTS_CURIO.1"]
+            .into_iter()
+            .flat_map(|id| {
+                [
+                    format!("base.loot.darkest/{}", id),
+                    format!("dlc.loot.darkest/{}", id),
+                ]
+            })
+            .map(|name| sources[&name][0].as_str())
+            .collect();
+        for source in &crypts {
+            assert!(source.contains("CRYPTS_ENEMY.1") || source.contains("CRYPTS_CURIO.1"));
+        }
+        for name in [
+            "base.loot.darkest/WEALD_ENEMY.1",
+            "dlc.loot.darkest/WEALD_ENEMY.1",
+        ] {
+            assert!(sources[name][0].ends_with("/WEALD_ENEMY.1"));
+        }
+
+        let mut drops: Vec<String> = Vec::new();
+        for content in plan.files.values() {
+            let content = String::from_utf8_lossy(content).to_string();
+            assert!(content.starts_with("// crypts loot\n"));
+            assert!(content.contains(
+                r#"loot: .id "CRYPTS_ENEMY" .difficulty 3 .dungeon "crypts" .chances 1 .type "gem" .amount 1"#
+            ));
+            drops.extend(content.lines().skip(1).map(|l| l.to_string()));
+        }
+        assert_eq!(drops.len(), 10);
+        let gold = drops
+            .iter()
+            .filter(|line| line.contains(r#".type "gold" .amount 500"#))
+            .count();
+        assert_eq!(gold, 2);
+    }
+
+    #[test]
+    fn test_scale_payout() {
+        let mut line = String::from(
+            r#"loot: .id "CRYPTS_CURIO" .difficulty 1 .dungeon "crypts" .chances 1 .type "trinket" .amount 1"#,
+        );
+        scale_payout(&mut line, 25);
+        assert_eq!(
+            line,
+            r#"loot: .id "CRYPTS_CURIO" .difficulty 1 .dungeon "crypts" .chances 1 .type "trinket" .amount 1"#
+        );
+
+        let mut line = String::from(
+            r#"loot: .id "CRYPTS_ENEMY" .difficulty 1 .dungeon "crypts" .chances 3 .type "gold" .amount 250"#,
+        );
+        scale_payout(&mut line, 150);
+        assert_eq!(
+            line,
+            r#"loot: .id "CRYPTS_ENEMY" .difficulty 1 .dungeon "crypts" .chances 3 .type "gold" .amount 375"#
+        );
+    }
+}
@@ -1,17 +1,25 @@
 use log::*;
-use rand::{Rng, rngs::StdRng};
-use std::collections::HashMap;
+use rand::Rng;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::error::Error;
-use std::fs;
-use std::fs::{File, OpenOptions};
+use std::fs::File;
 use std::io::BufReader;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
 
+use crate::darkest::{Entry, Number};
+use crate::plan::ModPlan;
+use crate::rand_enemy::assets::AssetIndex;
+use crate::rand_enemy::threat::Balancer;
+use crate::seed::SeedRng;
+
 #[derive(Debug, Clone)]
 pub struct Mash {
     name: String,
     id: String,
+    /// Directory of the file relative to the game directory, e.g. `dungeons/crypts` or the
+    /// dungeon directory within a DLC, so the override replaces the same file
+    dir: PathBuf,
     hall: Vec<String>,
     room: Vec<String>,
     boss: Vec<String>,
@@ -50,18 +58,26 @@ pub fn get_data_files(
         }
     }
 
+    // Sort for consistent results with the same seed, HashMap iteration order is randomized
+    dungeon_files.sort();
+
     Ok(dungeon_files)
 }
 
-pub fn extract_data(datafiles: &[PathBuf]) -> Result<Vec<Mash>, Box<dyn Error>> {
+pub fn extract_data(game_dir: &Path, datafiles: &[PathBuf]) -> Result<Vec<Mash>, Box<dyn Error>> {
     let mut mashes: Vec<Mash> = Vec::new();
     for dpath in datafiles {
         let fname = dpath.file_stem().unwrap().to_str().unwrap();
         let fname: Vec<&str> = fname.split('.').collect();
+        let dir = dpath
+            .parent()
+            .ok_or_else(|| format!("Invalid dungeon file: {}", dpath.display()))?
+            .strip_prefix(game_dir)?;
 
         let mut mash = Mash {
             name: String::from(fname[0]),
             id: String::from(fname[1]),
+            dir: dir.to_path_buf(),
             hall: Vec::new(),
             room: Vec::new(),
             boss: Vec::new(),
@@ -78,13 +94,13 @@ pub fn extract_data(datafiles: &[PathBuf]) -> Result<Vec<Mash>, Box<dyn Error>>
         // build the mash using the data from each line of the file
         // ignore lines that are not relevant
         for line in buf.lines() {
-            match line[0..4].to_string().trim() {
-                "hall" => mash.hall.push(String::from(line)),
-                "room" => mash.room.push(String::from(line)),
-                "boss" => mash.boss.push(String::from(line)),
-                "stal" => mash.stall.push(String::from(line)),
-                "name" => mash.named.push(String::from(line)),
-                _ => (),
+            match entry_kind(line) {
+                Some(EntryKind::Hall) => mash.hall.push(String::from(line)),
+                Some(EntryKind::Room) => mash.room.push(String::from(line)),
+                Some(EntryKind::Boss) => mash.boss.push(String::from(line)),
+                Some(EntryKind::Stall) => mash.stall.push(String::from(line)),
+                Some(EntryKind::Named) => mash.named.push(String::from(line)),
+                None => (),
             }
         }
 
@@ -94,198 +110,421 @@ pub fn extract_data(datafiles: &[PathBuf]) -> Result<Vec<Mash>, Box<dyn Error>>
     Ok(mashes)
 }
 
-pub fn randomize(
-    mod_dpath: &Path,
-    mashes: Vec<Mash>,
-    rng: StdRng,
-    rand_boss: bool,
-    rand_mash: bool,
-) {
-    let mut seed_rng: StdRng = rng;
-    let mut boss_groups: HashMap<String, Vec<Vec<String>>> = HashMap::new();
-    let mut hall_groups: HashMap<String, Vec<Vec<String>>> = HashMap::new();
-    let mut room_groups: HashMap<String, Vec<Vec<String>>> = HashMap::new();
-
-    if rand_boss {
-        info!("Randomizing boss spawns");
-        let mut boss_collection: HashMap<String, Vec<String>> = HashMap::new();
-        for mash in mashes.clone() {
-            match boss_collection.get_mut(&mash.id) {
-                Some(pr) => {
-                    let mut tmp = pr.to_owned();
-                    for s in mash.boss {
-                        tmp.push(s);
-                    }
-                    boss_collection.insert(mash.id, tmp);
-                }
-                None => {
-                    boss_collection.insert(mash.id, mash.boss);
-                }
-            }
-        }
-        for level in boss_collection.keys() {
-            let group = shuffle_mash_loc(
-                boss_collection.get(level).unwrap().clone(),
-                4,
-                &mut seed_rng,
-            );
-            boss_groups.insert(level.to_string(), group);
+/// Which entries of the mash files are randomized
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MashOptions {
+    pub boss: bool,
+    /// Hall and room encounters
+    pub monsters: bool,
+    /// Encounters which appear when the party stalls for too long
+    pub stall: bool,
+    pub named: bool,
+}
+
+/// Monsters which can spawn in each dungeon level after randomization, keyed as `name.id`
+#[derive(Debug, Clone, Default)]
+pub struct Spawns {
+    /// Hall, room and boss encounters
+    pub encounters: BTreeMap<String, Vec<String>>,
+    pub stall: BTreeMap<String, Vec<String>>,
+    pub named: BTreeMap<String, Vec<String>>,
+}
+
+/// Shuffled entries of each dungeon level, with one group for every dungeon of the level
+type LevelGroups = BTreeMap<String, Vec<Vec<String>>>;
+
+/// Kinds of entry in a mash file, in the order unchanged entries are written
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EntryKind {
+    Boss,
+    Named,
+    Stall,
+    Hall,
+    Room,
+}
+
+impl EntryKind {
+    const ALL: [EntryKind; 5] = [
+        EntryKind::Boss,
+        EntryKind::Named,
+        EntryKind::Stall,
+        EntryKind::Hall,
+        EntryKind::Room,
+    ];
+
+    /// Order each dungeon picks its groups in, kept stable so seeds reproduce the same mod
+    const PICK_ORDER: [EntryKind; 5] = [
+        EntryKind::Hall,
+        EntryKind::Room,
+        EntryKind::Boss,
+        EntryKind::Stall,
+        EntryKind::Named,
+    ];
+
+    fn lines(self, mash: &Mash) -> &[String] {
+        match self {
+            EntryKind::Boss => &mash.boss,
+            EntryKind::Named => &mash.named,
+            EntryKind::Stall => &mash.stall,
+            EntryKind::Hall => &mash.hall,
+            EntryKind::Room => &mash.room,
         }
     }
 
-    if rand_mash {
-        info!("Randomizing monster spawns");
-        let mut hall_collection: HashMap<String, Vec<String>> = HashMap::new();
-        for mash in mashes.clone() {
-            match hall_collection.get_mut(&mash.id) {
-                Some(pr) => {
-                    let mut tmp = pr.to_owned();
-                    for s in mash.hall {
-                        tmp.push(s);
-                    }
-                    hall_collection.insert(mash.id, tmp);
-                }
-                None => {
-                    hall_collection.insert(mash.id, mash.hall);
-                }
-            }
+    fn is_randomized(self, options: &MashOptions) -> bool {
+        match self {
+            EntryKind::Boss => options.boss,
+            EntryKind::Named => options.named,
+            EntryKind::Stall => options.stall,
+            EntryKind::Hall | EntryKind::Room => options.monsters,
         }
+    }
+}
 
-        let mut room_collection: HashMap<String, Vec<String>> = HashMap::new();
-        for mash in mashes.clone() {
-            match room_collection.get_mut(&mash.id) {
-                Some(pr) => {
-                    let mut tmp = pr.to_owned();
-                    for s in mash.room {
-                        tmp.push(s);
-                    }
-                    room_collection.insert(mash.id, tmp);
-                }
-                None => {
-                    room_collection.insert(mash.id, mash.room);
-                }
-            }
+pub fn randomize(
+    plan: &mut ModPlan,
+    mashes: Vec<Mash>,
+    rng: SeedRng,
+    options: &MashOptions,
+    balancer: Option<&Balancer>,
+    assets: Option<&AssetIndex>,
+) -> Result<Spawns, Box<dyn Error>> {
+    let mut seed_rng: SeedRng = rng;
+    // each dungeon included for a level receives one group of that level's entries
+    let mut dungeon_counts: BTreeMap<String, usize> = BTreeMap::new();
+    for mash in &mashes {
+        *dungeon_counts.entry(mash.id.clone()).or_default() += 1;
+    }
+    let original_lines: Vec<String> = mashes
+        .iter()
+        .flat_map(|mash| EntryKind::ALL.iter().flat_map(|kind| kind.lines(mash)))
+        .cloned()
+        .collect();
+    let mut written_lines: Vec<String> = Vec::new();
+    // entries with monsters the game could not load elsewhere stay in their own dungeon level
+    let mut kept_lines: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    // monsters which can spawn in each dungeon level for the spoiler
+    let mut spawns = Spawns::default();
+
+    // bosses are drawn first to keep the same groups as before the other kinds could be randomized
+    let mut draw_order: Vec<EntryKind> = vec![EntryKind::Boss];
+    draw_order.extend(
+        EntryKind::PICK_ORDER
+            .into_iter()
+            .filter(|kind| *kind != EntryKind::Boss),
+    );
+
+    let mut kind_groups: Vec<(EntryKind, LevelGroups)> = Vec::new();
+    for kind in draw_order {
+        if !kind.is_randomized(options) {
+            continue;
         }
+        info!("Randomizing {:?} spawns", kind);
 
-        for level in hall_collection.keys() {
-            let group = shuffle_mash_loc(
-                hall_collection.get(level).unwrap().clone(),
-                4,
-                &mut seed_rng,
-            );
-            hall_groups.insert(level.to_string(), group);
+        // the entries of every dungeon for each level, along with the base game entries of each
+        // dungeon which are the balancing reference
+        let mut collection: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut vanilla: BTreeMap<String, Vec<Vec<String>>> = BTreeMap::new();
+        for mash in &mashes {
+            let (placeable, kept) = match assets {
+                Some(assets) => assets.split_placeable(kind.lines(mash).to_vec()),
+                None => (kind.lines(mash).to_vec(), Vec::new()),
+            };
+            collection
+                .entry(mash.id.clone())
+                .or_default()
+                .extend(placeable);
+            kept_lines
+                .entry(format!("{}.{}", &mash.name, &mash.id))
+                .or_default()
+                .extend(kept);
+            vanilla
+                .entry(mash.id.clone())
+                .or_default()
+                .push(kind.lines(mash).to_vec());
         }
 
-        for level in room_collection.keys() {
-            let group = shuffle_mash_loc(
-                room_collection.get(level).unwrap().clone(),
-                4,
+        // bosses are never balanced as each dungeon level has so few of them
+        let kind_balancer = balancer.filter(|_| kind != EntryKind::Boss);
+        let mut groups: LevelGroups = BTreeMap::new();
+        for (step, (level, lines)) in collection.into_iter().enumerate() {
+            let group = shuffle_level(
+                lines,
+                &vanilla[&level],
+                dungeon_counts[&level],
+                kind_balancer.map(|b| (b, step + 1)),
                 &mut seed_rng,
             );
-            room_groups.insert(level.to_string(), group);
+            groups.insert(level, group);
         }
+        debug!("{:?} {:#?}", kind, &groups);
+        kind_groups.push((kind, groups));
     }
-
-    debug!("{:#?}", &boss_groups);
-    debug!("{:#?}", &hall_groups);
-    debug!("{:#?}", &room_groups);
+    kind_groups.sort_by_key(|(kind, _)| {
+        EntryKind::PICK_ORDER
+            .iter()
+            .position(|k| k == kind)
+            .unwrap_or_default()
+    });
 
     for mash in mashes {
-        let data: Vec<String> = match (rand_boss, rand_mash) {
-            // Both randomized: only named + stall (boss, hall, room added later)
-            (true, true) => mash
-                .named
-                .into_iter()
-                .chain(mash.stall.into_iter())
-                .collect(),
-            // Only boss randomized: include hall + room as-is
-            (true, false) => mash
-                .named
-                .into_iter()
-                .chain(mash.stall.into_iter())
-                .chain(mash.hall.into_iter())
-                .chain(mash.room.into_iter())
-                .collect(),
-            // Only monsters randomized: include boss as-is
-            (false, true) => mash
-                .boss
-                .into_iter()
-                .chain(mash.named.into_iter())
-                .chain(mash.stall.into_iter())
-                .collect(),
-            // Neither (fallback, shouldn't happen)
-            (false, false) => mash
-                .boss
-                .into_iter()
-                .chain(mash.named.into_iter())
-                .chain(mash.stall.into_iter())
-                .chain(mash.hall.into_iter())
-                .chain(mash.room.into_iter())
-                .collect(),
-        };
-        let mdir = Path::join(mod_dpath, Path::new(&mash.name));
+        let mut lines: Vec<String> = EntryKind::ALL
+            .iter()
+            .filter(|kind| !kind.is_randomized(options))
+            .flat_map(|kind| kind.lines(&mash).iter().cloned())
+            .collect();
+
+        for (_, groups) in kind_groups.iter_mut() {
+            if let Some(level_groups) = groups.get_mut(&mash.id)
+                && !level_groups.is_empty()
+            {
+                let gidx = seed_rng.random_range(0..level_groups.len());
+                lines.extend(level_groups.remove(gidx));
+            }
+        }
+        let level_name = format!("{}.{}", &mash.name, &mash.id);
+        lines.extend(kept_lines.remove(&level_name).unwrap_or_default());
+
         let mpath = Path::join(
-            &mdir,
+            &mash.dir,
             Path::new(&format!("{}.{}.mash.darkest", &mash.name, &mash.id)),
         );
+        let mut content = String::new();
+        for line in &lines {
+            content.push_str(&format!("{}\n", line));
+        }
+        plan.add_file(mpath, content);
 
-        let mut data_lines = vec![data.into_iter()];
-        if rand_mash && !hall_groups.is_empty() && !room_groups.is_empty() {
-            let hgidx = seed_rng.random_range(0..hall_groups.get(&mash.id).unwrap().len());
-            let hgroup = hall_groups
-                .get_mut(&mash.id)
-                .unwrap()
-                .remove(hgidx)
-                .into_iter();
-            data_lines.push(hgroup);
-
-            let rgidx = seed_rng.random_range(0..room_groups.get(&mash.id).unwrap().len());
-            let rgroup = room_groups
-                .get_mut(&mash.id)
-                .unwrap()
-                .remove(rgidx)
-                .into_iter();
-            data_lines.push(rgroup);
+        let kind_lines = |kind: EntryKind| -> Vec<String> {
+            lines
+                .iter()
+                .filter(|line| entry_kind(line) == Some(kind))
+                .cloned()
+                .collect()
+        };
+        spawns
+            .encounters
+            .insert(level_name.clone(), spawn_monsters(&lines));
+        let stall = unique_monsters(&kind_lines(EntryKind::Stall));
+        if !stall.is_empty() {
+            spawns.stall.insert(level_name.clone(), stall);
         }
+        let named = unique_monsters(&kind_lines(EntryKind::Named));
+        if !named.is_empty() {
+            spawns.named.insert(level_name, named);
+        }
+        written_lines.extend(lines);
+    }
+
+    verify_lines(&original_lines, &written_lines)?;
+
+    Ok(spawns)
+}
+
+/// Check every original spawn entry was written exactly once
+/// Weights are left out of the comparison since the hard mode ramp deliberately raises them.
+fn verify_lines(original: &[String], written: &[String]) -> Result<(), Box<dyn Error>> {
+    let count_entries = |lines: &[String]| {
+        let mut counts: BTreeMap<String, i32> = BTreeMap::new();
+        for mut entry in lines.iter().filter_map(|line| Entry::parse(line)) {
+            if let Some(weight) = weight_mut(&mut entry) {
+                weight.clear();
+            }
+            *counts.entry(entry.to_string()).or_default() += 1;
+        }
+        counts
+    };
+
+    let mut counts = count_entries(original);
+    for (entry, count) in count_entries(written) {
+        *counts.entry(entry).or_default() -= count;
+    }
+    let mismatched: Vec<String> = counts
+        .into_iter()
+        .filter(|(_, count)| *count != 0)
+        .map(|(entry, count)| format!("{} ({:+})", entry, -count))
+        .collect();
+    if !mismatched.is_empty() {
+        return Err(format!(
+            "Spawn entries were lost or duplicated: {}",
+            mismatched.join(", ")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Kind of a mash file line from its key, `None` for lines which are not spawn entries
+fn entry_kind(line: &str) -> Option<EntryKind> {
+    match line.get(0..4)?.trim() {
+        "hall" => Some(EntryKind::Hall),
+        "room" => Some(EntryKind::Room),
+        "boss" => Some(EntryKind::Boss),
+        "stal" => Some(EntryKind::Stall),
+        "name" => Some(EntryKind::Named),
+        _ => None,
+    }
+}
+
+/// Unique monsters which can spawn from the hall, room and boss lines of a mash file
+/// Monsters are listed after `.types`, otherwise any non-numeric value is taken as a monster.
+pub fn spawn_monsters(lines: &[String]) -> Vec<String> {
+    let encounters: Vec<String> = lines
+        .iter()
+        .filter(|line| {
+            matches!(
+                entry_kind(line),
+                Some(EntryKind::Hall | EntryKind::Room | EntryKind::Boss)
+            )
+        })
+        .cloned()
+        .collect();
+    unique_monsters(&encounters)
+}
 
-        if rand_boss && !boss_groups.is_empty() {
-            let bgidx = seed_rng.random_range(0..boss_groups.get(&mash.id).unwrap().len());
-            let bgroup = boss_groups
-                .get_mut(&mash.id)
-                .unwrap()
-                .remove(bgidx)
-                .into_iter();
-            data_lines.push(bgroup);
+/// Unique monsters of every entry, sorted by name
+fn unique_monsters(lines: &[String]) -> Vec<String> {
+    let mut monsters: Vec<String> = Vec::new();
+    for entry in lines.iter().filter_map(|line| Entry::parse(line)) {
+        for monster in entry_monsters(&entry) {
+            if !monsters.contains(&monster) {
+                monsters.push(monster);
+            }
         }
+    }
+    monsters.sort();
+    monsters
+}
+
+/// Every monster of a single mash entry in order, including repeats of the same monster
+pub fn entry_monsters(entry: &Entry) -> Vec<String> {
+    let values = match entry.get("types") {
+        Some(types) => types.to_vec(),
+        None => entry.values.clone(),
+    };
+    values
+        .into_iter()
+        .filter(|value| Number::parse(value).is_none())
+        .collect()
+}
 
-        fs::create_dir_all(mdir).unwrap();
-        let mut of = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(mpath)
-            .unwrap();
-
-        for g in data_lines {
-            for line in g {
-                of.write_fmt(format_args!("{}\n", line)).unwrap();
+/// Dungeons each monster spawns in from any entry of the base game, including bosses
+pub fn monster_dungeons(mashes: &[Mash]) -> BTreeMap<String, BTreeSet<String>> {
+    let mut dungeons: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for mash in mashes {
+        for kind in EntryKind::ALL {
+            for monster in unique_monsters(kind.lines(mash)) {
+                dungeons
+                    .entry(monster)
+                    .or_default()
+                    .insert(mash.name.clone());
             }
         }
     }
+    dungeons
+}
+
+/// Dungeons each monster spawns in from hall and room lines, e.g. `weald` or `crypts+weald`
+/// Bosses are left out so they never share a family with regular monsters.
+pub fn monster_families(mashes: &[Mash]) -> BTreeMap<String, String> {
+    let mut dungeons: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for mash in mashes {
+        for monster in spawn_monsters(&mash.hall)
+            .into_iter()
+            .chain(spawn_monsters(&mash.room))
+        {
+            dungeons
+                .entry(monster)
+                .or_default()
+                .insert(mash.name.clone());
+        }
+    }
+
+    dungeons
+        .into_iter()
+        .map(|(monster, names)| (monster, names.into_iter().collect::<Vec<_>>().join("+")))
+        .collect()
+}
+
+/// Number of shuffles tried for each dungeon level before settling for the closest to the base game
+const BALANCE_ATTEMPTS: usize = 100;
+
+/// Shuffle the entries of a dungeon level, keeping the threat of every group close to the base game
+/// when balancing, along with the level step used to ramp the threat in hard mode.
+fn shuffle_level(
+    loc_mash: Vec<String>,
+    vanilla: &[Vec<String>],
+    group_count: usize,
+    balance: Option<(&Balancer, usize)>,
+    seed_rng: &mut SeedRng,
+) -> Vec<Vec<String>> {
+    let Some((balancer, step)) = balance else {
+        return shuffle_mash_loc(loc_mash, group_count, seed_rng);
+    };
+
+    let reference = balancer.reference(vanilla);
+    let mut best: Option<(f64, Vec<Vec<String>>)> = None;
+    for _ in 0..BALANCE_ATTEMPTS {
+        let groups = shuffle_mash_loc(loc_mash.clone(), group_count, seed_rng);
+        let deviation = balancer.deviation(&groups, reference);
+        if best
+            .as_ref()
+            .is_none_or(|(closest, _)| deviation < *closest)
+        {
+            best = Some((deviation, groups));
+        }
+        if balancer.accepts(deviation) {
+            break;
+        }
+    }
+
+    let Some((deviation, mut groups)) = best else {
+        return Vec::new();
+    };
+    if !balancer.accepts(deviation) {
+        warn!(
+            "No balanced spawns found within the tolerance, using the closest at {:.0}% from the base game",
+            deviation * 100.0
+        );
+    }
+    for group in groups.iter_mut() {
+        balancer.ramp(group, step);
+    }
+    groups
 }
 
+/// Weight of a mash entry, either its `.chance` or the first value of older style lines
+pub fn weight_mut(entry: &mut Entry) -> Option<&mut String> {
+    if entry.get("chance").is_some() {
+        return entry
+            .get_mut("chance")
+            .and_then(|values| values.first_mut());
+    }
+    entry
+        .values
+        .first_mut()
+        .filter(|value| Number::parse(value).is_some())
+}
+
+/// Split the entries of a level into one group per dungeon, each line is moved whole so weights are kept
+/// Leftover entries go one each to the first groups, which are assigned to dungeons at random.
 fn shuffle_mash_loc(
     loc_mash: Vec<String>,
     group_count: usize,
-    seed_rng: &mut StdRng,
+    seed_rng: &mut SeedRng,
 ) -> Vec<Vec<String>> {
+    if group_count == 0 {
+        return Vec::new();
+    }
     let item_count = loc_mash.len() / group_count;
+    let remainder = loc_mash.len() % group_count;
     let mut tloc_mash = loc_mash;
     let mut groups: Vec<Vec<String>> = Vec::new();
 
-    for _ in 0..group_count {
+    for gidx in 0..group_count {
+        let group_size = item_count + usize::from(gidx < remainder);
         let mut group: Vec<String> = Vec::new();
-        while group.len() < item_count {
+        while group.len() < group_size {
             let rand_idx = seed_rng.random_range(0..tloc_mash.len());
             let line = &tloc_mash[rand_idx];
             group.push(line.to_string());
@@ -297,3 +536,162 @@ fn shuffle_mash_loc(
 
     groups
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed;
+
+    #[test]
+    fn test_spawn_monsters() {
+        let lines = vec![
+            String::from("hall: .chance 2 .types skeleton_soldier_A skeleton_arbalist_A"),
+            String::from("room: 3 necromancer_A skeleton_soldier_A"),
+            String::from("stall: .chance 1 .types ghoul_A"),
+        ];
+        assert_eq!(
+            spawn_monsters(&lines),
+            vec!["necromancer_A", "skeleton_arbalist_A", "skeleton_soldier_A"]
+        );
+    }
+
+    fn test_mash(name: &str, hall: &[&str], room: &[&str]) -> Mash {
+        Mash {
+            name: name.to_string(),
+            id: String::from("1"),
+            dir: Path::new("dungeons").join(name),
+            hall: hall.iter().map(|line| line.to_string()).collect(),
+            room: room.iter().map(|line| line.to_string()).collect(),
+            boss: vec![format!("boss: 1 {}_boss_A", name)],
+            stall: vec![format!("stall: 1 {}_stall_A", name)],
+            named: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_shuffle_mash_loc_keeps_leftovers() {
+        let lines: Vec<String> = (0..7)
+            .map(|idx| format!("hall: .chance {} .types monster_{}_A", idx + 1, idx))
+            .collect();
+        let groups = shuffle_mash_loc(lines.clone(), 3, &mut seed::create_rng("testseed00"));
+
+        let sizes: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+        assert_eq!(sizes, vec![3, 2, 2]);
+        let mut shuffled: Vec<String> = groups.into_iter().flatten().collect();
+        shuffled.sort();
+        assert_eq!(shuffled, lines);
+    }
+
+    #[test]
+    fn test_randomize_three_dungeons() {
+        let mashes = vec![
+            test_mash("crypts", &["hall: 2 a_A", "hall: 1 b_A"], &["room: 1 c_A"]),
+            test_mash("warrens", &["hall: 3 d_A"], &["room: 2 e_A", "room: 1 f_A"]),
+            test_mash("weald", &["hall: 1 g_A", "hall: 4 h_A"], &["room: 1 i_A"]),
+        ];
+        let mut plan = ModPlan::default();
+        let summary = randomize(
+            &mut plan,
+            mashes,
+            seed::create_rng("testseed00"),
+            &MashOptions {
+                boss: true,
+                monsters: true,
+                stall: true,
+                named: true,
+            },
+            None,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(summary.encounters.len(), 3);
+        let mut stall: Vec<String> = summary.stall.into_values().flatten().collect();
+        stall.sort();
+        assert_eq!(
+            stall,
+            vec!["crypts_stall_A", "warrens_stall_A", "weald_stall_A"]
+        );
+        let written: usize = plan
+            .files
+            .values()
+            .map(|content| String::from_utf8_lossy(content).lines().count())
+            .sum();
+        assert_eq!(written, 5 + 4 + 3 + 3);
+    }
+
+    #[test]
+    fn test_randomize_keeps_unloadable_spawns() {
+        let mashes = vec![
+            test_mash("crypts", &["hall: 2 a_A", "hall: 1 modded_A"], &[]),
+            test_mash("weald", &["hall: 1 b_A"], &[]),
+        ];
+        let assets: AssetIndex = ["a_A", "b_A"]
+            .iter()
+            .map(|m| (m.to_string(), format!("monsters/{}", m)))
+            .collect();
+        let mut plan = ModPlan::default();
+        let summary = randomize(
+            &mut plan,
+            mashes,
+            seed::create_rng("testseed00"),
+            &MashOptions {
+                monsters: true,
+                ..Default::default()
+            },
+            None,
+            Some(&assets),
+        )
+        .unwrap();
+
+        assert!(summary.encounters["crypts.1"].contains(&String::from("modded_A")));
+        assert!(!summary.encounters["weald.1"].contains(&String::from("modded_A")));
+    }
+
+    #[test]
+    fn test_randomize_writes_dlc_mash_in_its_dlc() {
+        let courtyard_dir =
+            Path::new("dlc/580100_crimson_court/features/crimson_court/dungeons/courtyard");
+        let mut courtyard = test_mash("courtyard", &["hall: 1 b_A"], &[]);
+        courtyard.dir = courtyard_dir.to_path_buf();
+        let mashes = vec![test_mash("crypts", &["hall: 2 a_A"], &[]), courtyard];
+        let mut plan = ModPlan::default();
+        randomize(
+            &mut plan,
+            mashes,
+            seed::create_rng("testseed00"),
+            &MashOptions {
+                monsters: true,
+                ..Default::default()
+            },
+            None,
+            None,
+        )
+        .unwrap();
+
+        let written: Vec<&PathBuf> = plan.files.keys().collect();
+        assert_eq!(
+            written,
+            vec![
+                &courtyard_dir.join("courtyard.1.mash.darkest"),
+                &Path::new("dungeons/crypts").join("crypts.1.mash.darkest"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_lines() {
+        let original = vec![
+            String::from("hall: .chance 2 .types a_A b_A"),
+            String::from("room: 1 c_A"),
+        ];
+        let ramped = vec![
+            String::from("room: 1 c_A"),
+            String::from("hall: .chance 3 .types a_A b_A"),
+            String::from("stall: .chance 1 .types d_A"),
+        ];
+        assert!(verify_lines(&original, &ramped[..2]).is_ok());
+        assert!(verify_lines(&original, &ramped).is_err());
+        assert!(verify_lines(&original, &ramped[..1]).is_err());
+    }
+}
@@ -1,2 +1,8 @@
+pub mod assets;
 pub mod boss;
+pub mod curio;
+pub mod loot;
 pub mod mash;
+pub mod monster;
+pub mod monster_skills;
+pub mod threat;
@@ -0,0 +1,291 @@
+use log::*;
+use rand::Rng;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::darkest::{Entry, Number};
+use crate::plan::ModPlan;
+use crate::seed::SeedRng;
+
+/// Data read from a monster `.info.darkest` file
+#[derive(Debug, Clone)]
+pub struct Monster {
+    /// Name of the monster variant, e.g. `cultist_brawler_A`
+    pub name: String,
+    /// Location of the file relative to the game directory, including the DLC directory of DLC
+    /// monsters, e.g. `monsters/cultist_brawler/cultist_brawler_A/cultist_brawler_A.info.darkest`
+    pub path: PathBuf,
+    pub data: Vec<String>,
+}
+
+#[cfg(test)]
+impl Monster {
+    /// Monster with its data lines, kept where the game keeps base game monsters
+    pub fn new(name: &str, data: Vec<String>) -> Monster {
+        Monster {
+            name: name.to_string(),
+            path: PathBuf::from(format!("monsters/{}/{}.info.darkest", name, name)),
+            data,
+        }
+    }
+}
+
+/// How the randomized monster stats lean compared to the base game
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// Shift of the average change as a fraction of the variance
+    fn bias(self) -> f64 {
+        match self {
+            Difficulty::Easy => -0.5,
+            Difficulty::Normal => 0.0,
+            Difficulty::Hard => 0.5,
+        }
+    }
+}
+
+/// Options for monster stat randomization
+#[derive(Debug, Clone)]
+pub struct MonsterOptions {
+    /// Maximum change to any stat as a percentage of its base value, before the difficulty shift
+    pub variance: u32,
+    pub difficulty: Difficulty,
+}
+
+/// Monster stats on the `stats` line, all other properties ending in `_resist` are resistances
+const STATS: &[&str] = &["hp", "def", "prot", "spd"];
+
+/// Helper function to get every monster data file below the game's `monsters` directory
+pub fn get_data_files(monsters_dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut monster_files: Vec<PathBuf> = Vec::new();
+    collect_info_files(monsters_dir, &mut monster_files)?;
+
+    // Sort for consistent results with the same seed, directory listings have no fixed order
+    monster_files.sort();
+
+    Ok(monster_files)
+}
+
+/// Read the monster files, keeping their location so the overrides replace the same files
+pub fn extract_data(
+    game_dir: &Path,
+    datafiles: &[PathBuf],
+) -> Result<Vec<Monster>, Box<dyn Error>> {
+    let mut monsters: Vec<Monster> = Vec::new();
+    for mpath in datafiles {
+        let name = mpath
+            .file_name()
+            .and_then(|f| f.to_str())
+            .and_then(|f| f.strip_suffix(".info.darkest"))
+            .ok_or_else(|| format!("Invalid monster file name: {}", mpath.display()))?;
+        monsters.push(Monster {
+            name: name.to_string(),
+            path: mpath.strip_prefix(game_dir)?.to_path_buf(),
+            data: fs::read_to_string(mpath)?
+                .lines()
+                .map(|l| l.to_string())
+                .collect(),
+        });
+    }
+
+    Ok(monsters)
+}
+
+/// Randomize the stats, resistances and skill damage of each monster
+/// Each stat gets its own change per monster, with every skill's damage changed by the same amount.
+pub fn randomize(monsters: Vec<Monster>, options: &MonsterOptions, rng: SeedRng) -> Vec<Monster> {
+    info!("Randomizing monster stats");
+    let mut seed_rng: SeedRng = rng;
+    let variance = f64::from(options.variance) / 100.0;
+    let bias = options.difficulty.bias() * variance;
+
+    let mut randomized: Vec<Monster> = Vec::new();
+    for mut monster in monsters {
+        let damage_factor = random_factor(variance, bias, &mut seed_rng);
+
+        for line in monster.data.iter_mut() {
+            let Some(mut entry) = Entry::parse(line) else {
+                continue;
+            };
+            let mut changed = false;
+            match entry.key.as_str() {
+                "stats" => {
+                    let names: Vec<String> = entry
+                        .props
+                        .iter()
+                        .map(|(name, _)| name.clone())
+                        .filter(|name| STATS.contains(&name.as_str()) || name.ends_with("_resist"))
+                        .collect();
+                    for name in names {
+                        let factor = random_factor(variance, bias, &mut seed_rng);
+                        changed |= scale_values(&mut entry, &name, factor);
+                    }
+                }
+                "skill" => changed |= scale_values(&mut entry, "dmg", damage_factor),
+                _ => (),
+            }
+            // only rewrite lines that were adjusted to keep everything else byte for byte
+            if changed {
+                *line = entry.to_string();
+            }
+        }
+
+        debug!(
+            "{} randomized with damage factor {}",
+            &monster.name, damage_factor
+        );
+        randomized.push(monster);
+    }
+
+    randomized
+}
+
+/// Add the monster files to the mod in the same location as the game's files
+pub fn write_data(plan: &mut ModPlan, monsters: &[Monster]) {
+    for monster in monsters {
+        let mut content = String::new();
+        for line in &monster.data {
+            content.push_str(&format!("{}\n", line));
+        }
+        plan.add_file(&monster.path, content);
+    }
+}
+
+fn collect_info_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(dir)? {
+        let path: PathBuf = entry?.path();
+        if path.is_dir() {
+            collect_info_files(&path, files)?;
+        } else if path.to_string_lossy().ends_with(".info.darkest") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Random multiplier within the variance around the difficulty shift
+fn random_factor(variance: f64, bias: f64, seed_rng: &mut SeedRng) -> f64 {
+    (1.0 + bias + seed_rng.random_range(-variance..=variance)).max(0.0)
+}
+
+/// Scale every value of a property, returns whether the entry was changed
+/// Whole numbers stay whole and the monster keeps at least one hit point.
+fn scale_values(entry: &mut Entry, prop: &str, factor: f64) -> bool {
+    let is_hp = prop == "hp";
+    let is_prot = prop == "prot";
+    let Some(values) = entry.get_mut(prop) else {
+        return false;
+    };
+
+    let mut changed = false;
+    for value in values.iter_mut() {
+        let Some(mut number) = Number::parse(value) else {
+            continue;
+        };
+        let scaled = number.value * factor;
+        let mut rounded = if number.value.fract() == 0.0 {
+            scaled.round()
+        } else {
+            (scaled * 100.0).round() / 100.0
+        };
+        if is_hp {
+            rounded = rounded.max(1.0);
+        }
+        // protection at or above 100% would make the monster immune to damage
+        if is_prot {
+            rounded = rounded.min(if number.percent { 95.0 } else { 0.95 });
+        }
+        number.value = rounded.max(0.0);
+        *value = number.to_string();
+        changed = true;
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed;
+
+    fn test_monster() -> Monster {
+        Monster::new(
+            "test_monster_A",
+            vec![
+                String::from("display: .size 1"),
+                String::from(
+                    "stats: .hp 20 .def 7.5% .prot 0.25 .spd 4 .stun_resist 25% .bleed_resist 0%",
+                ),
+                String::from(r#"skill: .id "claw" .type "melee" .atk 82.5% .dmg 4 8 .crit 6%"#),
+                String::from("monster_brain: .id test_monster"),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_randomize_no_variance() {
+        let options = MonsterOptions {
+            variance: 0,
+            difficulty: Difficulty::Normal,
+        };
+        let randomized = randomize(
+            vec![test_monster()],
+            &options,
+            seed::create_rng("testseed00"),
+        );
+        assert_eq!(randomized[0].data, test_monster().data);
+    }
+
+    #[test]
+    fn test_randomize_hard_difficulty() {
+        let options = MonsterOptions {
+            variance: 20,
+            difficulty: Difficulty::Hard,
+        };
+        let randomized = randomize(
+            vec![test_monster(); 50],
+            &options,
+            seed::create_rng("testseed00"),
+        );
+
+        let mut total_hp = 0.0;
+        for monster in &randomized {
+            let data = &monster.data;
+            assert_eq!(data[0], "display: .size 1");
+            assert_eq!(data[3], "monster_brain: .id test_monster");
+
+            // hard difficulty shifts the 20% variance up by half, so changes range from -10% to +30%
+            let stats = Entry::parse(&data[1]).unwrap();
+            let hp = Number::parse(&stats.get("hp").unwrap()[0]).unwrap().value;
+            assert!((18.0..=26.0).contains(&hp));
+            assert_eq!(stats.get("bleed_resist"), Some(&[String::from("0%")][..]));
+            total_hp += hp;
+
+            let skill = Entry::parse(&data[2]).unwrap();
+            let dmg: Vec<f64> = skill
+                .get("dmg")
+                .unwrap()
+                .iter()
+                .map(|v| Number::parse(v).unwrap().value)
+                .collect();
+            assert!((4.0..=5.0).contains(&dmg[0]) && (7.0..=10.0).contains(&dmg[1]));
+            assert_eq!(skill.get("atk"), Some(&[String::from("82.5%")][..]));
+        }
+        assert!(total_hp / 50.0 > 20.5);
+    }
+
+    #[test]
+    fn test_scale_values_bounds() {
+        let mut entry = Entry::parse("stats: .hp 2 .prot 0.8 .def 10%").unwrap();
+        assert!(scale_values(&mut entry, "hp", 0.1));
+        assert!(scale_values(&mut entry, "prot", 1.5));
+        assert!(scale_values(&mut entry, "def", 1.25));
+        assert!(!scale_values(&mut entry, "spd", 2.0));
+        assert_eq!(entry.to_string(), "stats: .hp 1 .prot 0.95 .def 13%");
+    }
+}
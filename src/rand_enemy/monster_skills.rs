@@ -0,0 +1,236 @@
+use log::*;
+use rand::Rng;
+use std::collections::BTreeMap;
+
+use crate::darkest::Entry;
+use crate::rand_enemy::monster::Monster;
+use crate::seed::SeedRng;
+
+/// Skill properties which stay with the monster, the id is referenced by its AI brain and
+/// animations and the launch positions keep the skill usable from where the monster stands
+const KEPT_PROPERTIES: &[&str] = &["id", "launch"];
+
+/// An attack skill of a monster, skills without damage such as moves or summons are never traded
+#[derive(Debug, Clone, PartialEq)]
+struct MonsterSkill {
+    monster: String,
+    entry: Entry,
+}
+
+impl MonsterSkill {
+    fn id(&self) -> String {
+        self.entry
+            .get("id")
+            .and_then(|values| values.first())
+            .map(|id| id.trim_matches('"').to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Shuffle attack skills between monsters of the same size, level tier and dungeon family
+/// Each monster keeps its skill ids, so its AI brain and animations stay valid, and receives the
+/// attack data of the traded skill. Returns the skills each monster received as `monster.skill`.
+pub fn randomize(
+    monsters: Vec<Monster>,
+    families: &BTreeMap<String, String>,
+    rng: SeedRng,
+) -> (Vec<Monster>, BTreeMap<String, Vec<String>>) {
+    info!("Randomizing monster skills");
+    let mut seed_rng: SeedRng = rng;
+    let mut monsters = monsters;
+    let mut summary: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    // group the monsters which can trade skills, using a sorted map for consistent output with the same seed
+    let mut groups: BTreeMap<(String, String, String), Vec<usize>> = BTreeMap::new();
+    for (midx, monster) in monsters.iter().enumerate() {
+        let (Some(size), Some(tier), Some(family)) = (
+            monster_size(monster),
+            monster_tier(monster),
+            families.get(&monster.name),
+        ) else {
+            continue;
+        };
+        groups
+            .entry((size, tier.to_string(), family.clone()))
+            .or_default()
+            .push(midx);
+    }
+
+    for ((size, tier, family), midxs) in groups {
+        if midxs.len() < 2 {
+            continue;
+        }
+        debug!(
+            "Trading skills between size {} tier {} {} monsters: {:?}",
+            &size,
+            &tier,
+            &family,
+            midxs
+                .iter()
+                .map(|midx| &monsters[*midx].name)
+                .collect::<Vec<_>>()
+        );
+
+        let mut skill_collection: Vec<MonsterSkill> = midxs
+            .iter()
+            .flat_map(|midx| attack_skills(&monsters[*midx]))
+            .collect();
+
+        for midx in midxs {
+            let monster = &mut monsters[midx];
+            let mut received: Vec<String> = Vec::new();
+            for line in monster.data.iter_mut() {
+                let Some(entry) = Entry::parse(line) else {
+                    continue;
+                };
+                if !is_attack_skill(&entry) || skill_collection.is_empty() {
+                    continue;
+                }
+                let rand_idx = seed_rng.random_range(0..skill_collection.len());
+                let skill = skill_collection.remove(rand_idx);
+                received.push(format!("{}.{}", &skill.monster, skill.id()));
+                *line = trade_skill(&entry, &skill.entry).to_string();
+            }
+            summary.insert(monster.name.clone(), received);
+        }
+    }
+
+    (monsters, summary)
+}
+
+/// Size of the monster in ranks from its `display` line
+fn monster_size(monster: &Monster) -> Option<String> {
+    monster
+        .data
+        .iter()
+        .filter_map(|line| Entry::parse(line))
+        .find(|entry| entry.key == "display")
+        .and_then(|entry| entry.get("size").and_then(|v| v.first().cloned()))
+}
+
+/// Level tier of the monster from its variant suffix, e.g. `A` for `cultist_brawler_A`
+fn monster_tier(monster: &Monster) -> Option<&str> {
+    monster
+        .name
+        .rsplit_once('_')
+        .map(|(_, tier)| tier)
+        .filter(|tier| ["A", "B", "C"].contains(tier))
+}
+
+fn is_attack_skill(entry: &Entry) -> bool {
+    entry.key == "skill" && entry.get("id").is_some() && entry.get("dmg").is_some()
+}
+
+fn attack_skills(monster: &Monster) -> Vec<MonsterSkill> {
+    monster
+        .data
+        .iter()
+        .filter_map(|line| Entry::parse(line))
+        .filter(is_attack_skill)
+        .map(|entry| MonsterSkill {
+            monster: monster.name.clone(),
+            entry,
+        })
+        .collect()
+}
+
+/// Skill line with the kept properties of the original skill and everything else from the traded skill
+fn trade_skill(original: &Entry, traded: &Entry) -> Entry {
+    let mut props: Vec<(String, Vec<String>)> = Vec::new();
+    for name in KEPT_PROPERTIES {
+        if let Some(values) = original.get(name) {
+            props.push((name.to_string(), values.to_vec()));
+        }
+    }
+    props.extend(
+        traded
+            .props
+            .iter()
+            .filter(|(name, _)| !KEPT_PROPERTIES.contains(&name.as_str()))
+            .cloned(),
+    );
+
+    Entry {
+        key: original.key.clone(),
+        values: original.values.clone(),
+        props,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed;
+
+    fn test_monster(name: &str, size: u8, skills: &[&str]) -> Monster {
+        let mut data = vec![format!("display: .size {}", size)];
+        for skill in skills {
+            data.push(format!(
+                r#"skill: .id "{}" .type "melee" .atk 80% .dmg 2 4 .launch 12 .target 12 .effect "{}_effect""#,
+                skill, skill
+            ));
+        }
+        data.push(String::from(r#"skill: .id "move" .type "move" .launch 34"#));
+        Monster::new(name, data)
+    }
+
+    #[test]
+    fn test_randomize_keeps_ids_and_groups() {
+        let monsters = vec![
+            test_monster("brawler_A", 1, &["stab", "slash"]),
+            test_monster("acolyte_A", 1, &["curse", "blast"]),
+            test_monster("brute_A", 2, &["smash"]),
+            test_monster("brawler_B", 1, &["stab_b"]),
+        ];
+        let families: BTreeMap<String, String> = ["brawler_A", "acolyte_A", "brute_A", "brawler_B"]
+            .into_iter()
+            .map(|name| (name.to_string(), String::from("weald")))
+            .collect();
+
+        let (randomized, summary) =
+            randomize(monsters.clone(), &families, seed::create_rng("testseed00"));
+
+        // monsters without a partner of the same size and tier are left alone
+        assert_eq!(randomized[2].data, monsters[2].data);
+        assert_eq!(randomized[3].data, monsters[3].data);
+        assert_eq!(summary.len(), 2);
+
+        let mut traded: Vec<String> = summary.values().flatten().cloned().collect();
+        traded.sort();
+        assert_eq!(
+            traded,
+            vec![
+                "acolyte_A.blast",
+                "acolyte_A.curse",
+                "brawler_A.slash",
+                "brawler_A.stab"
+            ]
+        );
+
+        for (monster, original) in randomized.iter().zip(&monsters).take(2) {
+            for (line, original_line) in monster.data.iter().zip(&original.data) {
+                let (entry, original_entry) = (
+                    Entry::parse(line).unwrap(),
+                    Entry::parse(original_line).unwrap(),
+                );
+                assert_eq!(entry.get("id"), original_entry.get("id"));
+                assert_eq!(entry.get("launch"), original_entry.get("launch"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_trade_skill() {
+        let original =
+            Entry::parse(r#"skill: .id "stab" .type "melee" .atk 80% .dmg 2 4 .launch 12"#)
+                .unwrap();
+        let traded = Entry::parse(
+            r#"skill: .id "claw" .type "ranged" .atk 70% .dmg 3 5 .launch 34 .target ~1234"#,
+        )
+        .unwrap();
+        assert_eq!(
+            trade_skill(&original, &traded).to_string(),
+            r#"skill: .id "stab" .launch 12 .type "ranged" .atk 70% .dmg 3 5 .target ~1234"#
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use log::*;
+use std::collections::BTreeMap;
+
+use crate::darkest::{Entry, Number};
+use crate::rand_enemy::mash;
+use crate::rand_enemy::monster::Monster;
+
+/// Options for keeping randomized encounters close to the threat of the base game
+#[derive(Debug, Clone)]
+pub struct BalanceOptions {
+    /// Largest change to the total and peak threat of a dungeon level as a percentage of the base game
+    pub tolerance: u32,
+    /// Hard mode increase in the likelihood of stronger encounters per dungeon level as a percentage
+    pub hard_ramp: Option<u32>,
+}
+
+/// Total and peak threat of the hall or room entries of a dungeon level
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GroupThreat {
+    pub total: f64,
+    pub peak: f64,
+}
+
+/// Threat scores of every monster used to judge the shuffled mash entries
+#[derive(Debug, Clone)]
+pub struct Balancer {
+    options: BalanceOptions,
+    threats: BTreeMap<String, f64>,
+}
+
+impl Balancer {
+    /// Score the monsters as they will be in the mod, so randomized stats are taken into account
+    pub fn new(monsters: &[Monster], options: BalanceOptions) -> Balancer {
+        Balancer {
+            options,
+            threats: monsters
+                .iter()
+                .map(|monster| (monster.name.clone(), monster_threat(monster)))
+                .collect(),
+        }
+    }
+
+    /// Threat of a single mash entry as the sum of its monsters
+    /// Monsters without a known score, such as ones added by other mods, count as no threat.
+    pub fn entry_threat(&self, line: &str) -> f64 {
+        let Some(entry) = Entry::parse(line) else {
+            return 0.0;
+        };
+        mash::entry_monsters(&entry)
+            .iter()
+            .map(|monster| match self.threats.get(monster) {
+                Some(threat) => *threat,
+                None => {
+                    debug!("No threat score for monster {}", monster);
+                    0.0
+                }
+            })
+            .sum()
+    }
+
+    pub fn measure(&self, lines: &[String]) -> GroupThreat {
+        lines.iter().map(|line| self.entry_threat(line)).fold(
+            GroupThreat::default(),
+            |acc, threat| GroupThreat {
+                total: acc.total + threat,
+                peak: acc.peak.max(threat),
+            },
+        )
+    }
+
+    /// Average threat of a dungeon level across the base game dungeons
+    pub fn reference(&self, vanilla: &[Vec<String>]) -> GroupThreat {
+        if vanilla.is_empty() {
+            return GroupThreat::default();
+        }
+        let count = vanilla.len() as f64;
+        let sum = vanilla.iter().map(|lines| self.measure(lines)).fold(
+            GroupThreat::default(),
+            |acc, threat| GroupThreat {
+                total: acc.total + threat.total,
+                peak: acc.peak + threat.peak,
+            },
+        );
+        GroupThreat {
+            total: sum.total / count,
+            peak: sum.peak / count,
+        }
+    }
+
+    /// Largest relative difference of any group's total or peak threat from the reference
+    pub fn deviation(&self, groups: &[Vec<String>], reference: GroupThreat) -> f64 {
+        let relative = |value: f64, base: f64| {
+            if base > 0.0 {
+                (value - base).abs() / base
+            } else {
+                0.0
+            }
+        };
+        groups
+            .iter()
+            .map(|lines| {
+                let threat = self.measure(lines);
+                relative(threat.total, reference.total).max(relative(threat.peak, reference.peak))
+            })
+            .fold(0.0, f64::max)
+    }
+
+    pub fn accepts(&self, deviation: f64) -> bool {
+        deviation <= f64::from(self.options.tolerance) / 100.0
+    }
+
+    /// Make the entries stronger than the group average more likely in hard mode
+    /// The increase grows with each dungeon level step, starting at one for the first level.
+    pub fn ramp(&self, lines: &mut [String], step: usize) {
+        let Some(hard_ramp) = self.options.hard_ramp else {
+            return;
+        };
+        if lines.is_empty() {
+            return;
+        }
+        let factor = 1.0 + f64::from(hard_ramp) / 100.0 * step as f64;
+        let average = self.measure(lines).total / lines.len() as f64;
+
+        for line in lines.iter_mut() {
+            if self.entry_threat(line) <= average {
+                continue;
+            }
+            let Some(mut entry) = Entry::parse(line) else {
+                continue;
+            };
+            if let Some(weight) = mash::weight_mut(&mut entry)
+                && let Some(mut number) = Number::parse(weight)
+            {
+                number.value = (number.value * factor).round().max(number.value);
+                *weight = number.to_string();
+                *line = entry.to_string();
+            }
+        }
+    }
+}
+
+/// Threat score of a monster from its stats and attack skills
+/// Effective hit points after dodge and protection plus twice its average damage per turn, adjusted for speed.
+pub fn monster_threat(monster: &Monster) -> f64 {
+    let entries: Vec<Entry> = monster
+        .data
+        .iter()
+        .filter_map(|line| Entry::parse(line))
+        .collect();
+    let stat = |name: &str| {
+        entries
+            .iter()
+            .find(|e| e.key == "stats")
+            .and_then(|e| e.get(name))
+            .and_then(|values| values.first())
+            .and_then(|value| Number::parse(value))
+    };
+    // percentages and decimals are both used for chances in the game files
+    let fraction = |number: Option<Number>| {
+        number
+            .map(|n| if n.percent { n.value / 100.0 } else { n.value })
+            .unwrap_or(0.0)
+            .clamp(0.0, 0.95)
+    };
+
+    let hp = stat("hp").map(|n| n.value).unwrap_or(0.0);
+    let effective_hp = hp / ((1.0 - fraction(stat("def"))) * (1.0 - fraction(stat("prot"))));
+
+    let damages: Vec<f64> = entries
+        .iter()
+        .filter(|e| e.key == "skill")
+        .filter_map(|e| {
+            let dmg: Vec<f64> = e
+                .get("dmg")?
+                .iter()
+                .filter_map(|v| Number::parse(v))
+                .map(|n| n.value)
+                .collect();
+            let crit = fraction(
+                e.get("crit")
+                    .and_then(|v| v.first())
+                    .and_then(|v| Number::parse(v)),
+            );
+            (!dmg.is_empty()).then(|| dmg.iter().sum::<f64>() / dmg.len() as f64 * (1.0 + crit))
+        })
+        .collect();
+    let damage = if damages.is_empty() {
+        0.0
+    } else {
+        damages.iter().sum::<f64>() / damages.len() as f64
+    };
+    let speed = stat("spd").map(|n| n.value).unwrap_or(0.0).max(0.0);
+
+    effective_hp + 2.0 * damage * (1.0 + speed / 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_monster(name: &str, hp: u32, dmg: u32) -> Monster {
+        Monster::new(
+            name,
+            vec![
+                format!("stats: .hp {} .def 0% .prot 0 .spd 0", hp),
+                format!(
+                    r#"skill: .id "hit" .type "melee" .dmg {} {} .crit 0%"#,
+                    dmg, dmg
+                ),
+            ],
+        )
+    }
+
+    fn test_balancer(hard_ramp: Option<u32>) -> Balancer {
+        Balancer::new(
+            &[
+                test_monster("weak_A", 10, 1),
+                test_monster("strong_A", 40, 5),
+            ],
+            BalanceOptions {
+                tolerance: 20,
+                hard_ramp,
+            },
+        )
+    }
+
+    #[test]
+    fn test_monster_threat() {
+        let mut monster = test_monster("test_A", 20, 4);
+        assert_eq!(monster_threat(&monster), 28.0);
+
+        monster.data[0] = String::from("stats: .hp 20 .def 50% .prot 0.5 .spd 5");
+        assert_eq!(monster_threat(&monster), 80.0 + 12.0);
+    }
+
+    #[test]
+    fn test_measure_and_deviation() {
+        let balancer = test_balancer(None);
+        let lines = vec![
+            String::from("hall: .chance 2 .types weak_A weak_A"),
+            String::from("hall: 1 strong_A unknown_A"),
+        ];
+        assert_eq!(
+            balancer.measure(&lines),
+            GroupThreat {
+                total: 24.0 + 50.0,
+                peak: 50.0
+            }
+        );
+
+        let reference = balancer.reference(std::slice::from_ref(&lines));
+        assert_eq!(
+            balancer.deviation(std::slice::from_ref(&lines), reference),
+            0.0
+        );
+        let weaker = vec![lines[0].clone(), lines[0].clone()];
+        assert!(!balancer.accepts(balancer.deviation(&[weaker], reference)));
+    }
+
+    #[test]
+    fn test_ramp_only_in_hard_mode() {
+        let mut lines = vec![
+            String::from("hall: .chance 2 .types weak_A weak_A"),
+            String::from("hall: 3 strong_A"),
+        ];
+        test_balancer(None).ramp(&mut lines, 2);
+        assert_eq!(lines[1], "hall: 3 strong_A");
+
+        test_balancer(Some(25)).ramp(&mut lines, 2);
+        assert_eq!(lines[0], "hall: .chance 2 .types weak_A weak_A");
+        assert_eq!(lines[1], "hall: 5 strong_A");
+    }
+}
@@ -1,92 +1,327 @@
-use rand::rngs::StdRng;
-use rand::seq::IteratorRandom;
-use serde_json::Value;
+use log::*;
+use rand::Rng;
+use rand::seq::{IteratorRandom, SliceRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     error::Error,
     fs::File,
-    io::{BufReader, BufWriter},
+    io::BufReader,
     path::PathBuf,
 };
 
-pub fn parse_from_file(file_path: &PathBuf) -> Result<Value, Box<dyn Error>> {
+use crate::seed::SeedRng;
+
+/// Number of time points available while camping, no skill can cost more
+const MAX_CAMP_TIME: u32 = 12;
+/// Highest use limit a skill can be given when jittering costs
+const MAX_USE_LIMIT: u32 = 3;
+
+/// Contents of `default.camping_skills.json`
+/// Fields which are not randomized are kept as is so they are written back unchanged.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CampingSkills {
+    pub skills: Vec<CampingSkill>,
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CampingSkill {
+    pub id: String,
+    /// Time points the skill takes to use
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<u32>,
+    /// Number of times the skill can be used each camp
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_limit: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub effects: Vec<CampingEffect>,
+    #[serde(default)]
+    pub hero_classes: Vec<String>,
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CampingEffect {
+    /// Who the effect applies to, e.g. `self` or `party_other`
+    pub selection: String,
+    #[serde(rename = "type")]
+    pub effect_type: String,
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+impl CampingSkills {
+    /// Reject data the game would not be able to use
+    pub fn validate(&self) -> Result<(), String> {
+        for skill in &self.skills {
+            if let Some(cost) = skill.cost
+                && !(1..=MAX_CAMP_TIME).contains(&cost)
+            {
+                return Err(format!(
+                    "Camping skill '{}' has an invalid cost of {}",
+                    &skill.id, cost
+                ));
+            }
+            if skill.use_limit == Some(0) {
+                return Err(format!(
+                    "Camping skill '{}' has a use limit of 0",
+                    &skill.id
+                ));
+            }
+            if skill
+                .effects
+                .iter()
+                .any(|e| e.selection.is_empty() || e.effect_type.is_empty())
+            {
+                return Err(format!(
+                    "Camping skill '{}' has an effect without a selection or type",
+                    &skill.id
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_from_file(file_path: &PathBuf) -> Result<CampingSkills, Box<dyn Error>> {
     let file = File::open(file_path);
     let reader = BufReader::new(file?);
     serde_json::from_reader(reader).map_err(|e| e.into())
 }
 
-pub fn randomize(mut skills_data: Value, rng: StdRng) -> Result<Value, Box<dyn Error>> {
-    let mut seed_rng: StdRng = rng;
+/// Options for camping skill randomization
+#[derive(Debug, Clone)]
+pub struct CampingOptions {
+    /// Maximum number of class specific skills any class can be assigned
+    pub max_per_class: u8,
+    /// Minimum number of class specific skills every class is guaranteed
+    pub min_per_class: u8,
+    /// Skills available to every class which are never randomized
+    pub shared_skills: Vec<String>,
+    /// Assign each skill to exactly as many different classes as in the base game
+    pub keep_owner_count: bool,
+    /// Trade the effect lists between the randomized skills
+    pub shuffle_effects: bool,
+    /// Maximum change to each skill's time cost and use limit, zero leaves them unchanged
+    pub cost_jitter: u8,
+}
+
+impl Default for CampingOptions {
+    fn default() -> Self {
+        CampingOptions {
+            // Each class can have at most 7 skills, ignoring the 3 common skills leaves 4 slots.
+            max_per_class: 4,
+            min_per_class: 0,
+            shared_skills: vec![
+                String::from("encourage"),
+                String::from("first_aid"),
+                String::from("pep_talk"),
+                String::from("hobby"),
+            ],
+            keep_owner_count: false,
+            shuffle_effects: false,
+            cost_jitter: 0,
+        }
+    }
+}
+
+impl CampingOptions {
+    fn is_shared(&self, skill: &CampingSkill) -> bool {
+        self.shared_skills.contains(&skill.id)
+    }
+}
+
+pub fn randomize(
+    mut skills_data: CampingSkills,
+    options: &CampingOptions,
+    rng: SeedRng,
+) -> Result<CampingSkills, Box<dyn Error>> {
+    let mut seed_rng: SeedRng = rng;
     let mut class_assigned_count: HashMap<String, u8> = HashMap::new();
 
     // The first camping skill should be `Encourage` which is available to all heroes.
     // Extract the full list of heroes for later assignment from this skill.
-    let class_list: Option<Vec<String>> = skills_data
-        .pointer("/skills/0/hero_classes")
-        .and_then(|v| v.as_array())
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|val| val.as_str().map(|s| s.to_string()))
-                .collect()
-        });
-    if let Some(class_names) = class_list {
-        for name in class_names {
-            class_assigned_count.insert(name, 0);
+    if let Some(first_skill) = skills_data.skills.first() {
+        for name in &first_skill.hero_classes {
+            class_assigned_count.insert(name.clone(), 0);
         }
     }
 
-    if let Some(skill_list) = skills_data
-        .pointer_mut("/skills")
-        .and_then(|v| v.as_array_mut())
-    {
-        for skill in skill_list {
-            // Skip the skills assigned to all classes.
-            if let Some(id) = skill.get_mut("id").and_then(|v| v.as_str())
-                && ["encourage", "first_aid", "pep_talk", "hobby"].contains(&id)
-            {
-                continue;
+    for skill in skills_data.skills.iter_mut() {
+        // Skip the skills assigned to all classes.
+        if options.is_shared(skill) {
+            continue;
+        }
+        // Skip any skill that may never have been assigned to any class in the base game.
+        let count = skill.hero_classes.len();
+        if count == 0 {
+            continue;
+        }
+
+        let hero_classes = &mut skill.hero_classes;
+        hero_classes.clear();
+        for _ in 0..count {
+            // If a class already has the maximum assigned skills, it cannot be assigned more so ignore it in the remaining choices.
+            let mut available_classes: Vec<_> = class_assigned_count
+                .iter()
+                .filter(|(_, v)| **v < options.max_per_class)
+                .filter(|(k, _)| !options.keep_owner_count || !hero_classes.contains(k))
+                .map(|(k, _)| k.clone())
+                .collect();
+            // When keeping the number of owners the limit is relaxed rather than dropping an owner.
+            if available_classes.is_empty() && options.keep_owner_count {
+                available_classes = class_assigned_count
+                    .keys()
+                    .filter(|k| !hero_classes.contains(k))
+                    .cloned()
+                    .collect();
             }
-            if let Some(hero_classes) = skill.get_mut("hero_classes").and_then(|c| c.as_array_mut())
-            {
-                // Skip any skill that may never have been assigned to any class in the base game.
-                let count = hero_classes.len();
-                if count == 0 {
-                    continue;
+            // Make sure to sort the results to always ensure consistent output for the same seed.
+            available_classes.sort();
+            if let Some(selected_class) = available_classes.into_iter().choose(&mut seed_rng) {
+                hero_classes.push(selected_class.clone());
+                if let Some(entry) = class_assigned_count.get_mut(&selected_class) {
+                    *entry += 1;
                 }
+            }
+        }
+    }
 
-                hero_classes.clear();
-                for _ in 0..count {
-                    // We ignore common skills leaving only 4 slots as each class can have at most 7 skills.
-                    // If a class already has 4 assigned skills, it cannot be assigned more so ignore it in the remaining choices.
-                    let mut available_classes: Vec<_> = class_assigned_count
-                        .iter()
-                        .filter(|(_, v)| **v < 4)
-                        .map(|(k, _)| k.clone())
-                        .collect();
-                    // Make sure to sort the results to always ensure consistent output for the same seed.
-                    available_classes.sort();
-                    if let Some(selected_class) =
-                        available_classes.into_iter().choose(&mut seed_rng)
-                    {
-                        hero_classes.push(Value::String(selected_class.clone()));
-                        if let Some(entry) = class_assigned_count.get_mut(&selected_class) {
-                            *entry += 1;
-                        }
+    if options.min_per_class > 0 {
+        guarantee_minimum(
+            &mut skills_data.skills,
+            &mut class_assigned_count,
+            options,
+            &mut seed_rng,
+        );
+    }
+
+    if options.shuffle_effects {
+        shuffle_effects(&mut skills_data.skills, options, &mut seed_rng);
+    }
+
+    if options.cost_jitter > 0 {
+        jitter_costs(&mut skills_data.skills, options, &mut seed_rng);
+    }
+
+    // Never hand back data the game cannot use.
+    skills_data.validate()?;
+
+    // Return the modified skill data.
+    Ok(skills_data)
+}
+
+/// Move skills from classes with spare skills to classes below the minimum
+fn guarantee_minimum(
+    skill_list: &mut [CampingSkill],
+    class_assigned_count: &mut HashMap<String, u8>,
+    options: &CampingOptions,
+    seed_rng: &mut SeedRng,
+) {
+    let mut class_names: Vec<String> = class_assigned_count.keys().cloned().collect();
+    class_names.sort();
+
+    for class_name in class_names {
+        while class_assigned_count[&class_name] < options.min_per_class {
+            // Any randomized skill this class does not have, owned by a class that can spare it.
+            let mut candidates: Vec<(usize, usize)> = Vec::new();
+            for (sidx, skill) in skill_list.iter().enumerate() {
+                if options.is_shared(skill) || skill.hero_classes.contains(&class_name) {
+                    continue;
+                }
+                for (cidx, owner) in skill.hero_classes.iter().enumerate() {
+                    if class_assigned_count
+                        .get(owner)
+                        .is_some_and(|n| *n > options.min_per_class)
+                    {
+                        candidates.push((sidx, cidx));
                     }
                 }
             }
+
+            let Some((sidx, cidx)) = candidates.into_iter().choose(seed_rng) else {
+                warn!(
+                    "Unable to give {} the minimum of {} camping skills",
+                    &class_name, options.min_per_class
+                );
+                break;
+            };
+            let owner = &mut skill_list[sidx].hero_classes[cidx];
+            if let Some(count) = class_assigned_count.get_mut(owner) {
+                *count -= 1;
+            }
+            *owner = class_name.clone();
+            if let Some(count) = class_assigned_count.get_mut(&class_name) {
+                *count += 1;
+            }
         }
     }
+}
 
-    // Return the modified skill data.
-    Ok(skills_data)
+/// Trade the whole effect list of each randomized skill with another randomized skill
+fn shuffle_effects(
+    skill_list: &mut [CampingSkill],
+    options: &CampingOptions,
+    seed_rng: &mut SeedRng,
+) {
+    let sidxs: Vec<usize> = skill_list
+        .iter()
+        .enumerate()
+        .filter(|(_, skill)| !options.is_shared(skill) && !skill.effects.is_empty())
+        .map(|(sidx, _)| sidx)
+        .collect();
+    let mut effects: Vec<Vec<CampingEffect>> = sidxs
+        .iter()
+        .map(|sidx| std::mem::take(&mut skill_list[*sidx].effects))
+        .collect();
+    effects.shuffle(seed_rng);
+    for (sidx, skill_effects) in sidxs.into_iter().zip(effects) {
+        skill_list[sidx].effects = skill_effects;
+    }
+}
+
+/// Adjust the time cost and use limit of each randomized skill within the jitter and game bounds
+fn jitter_costs(skill_list: &mut [CampingSkill], options: &CampingOptions, seed_rng: &mut SeedRng) {
+    let jitter = i64::from(options.cost_jitter);
+    for skill in skill_list
+        .iter_mut()
+        .filter(|skill| !options.is_shared(skill))
+    {
+        if let Some(cost) = skill.cost.as_mut() {
+            let jittered = i64::from(*cost) + seed_rng.random_range(-jitter..=jitter);
+            *cost = jittered.clamp(1, i64::from(MAX_CAMP_TIME)) as u32;
+        }
+        if let Some(use_limit) = skill.use_limit.as_mut() {
+            let jittered = i64::from(*use_limit) + seed_rng.random_range(-jitter..=jitter);
+            *use_limit = jittered.clamp(1, i64::from(MAX_USE_LIMIT.max(*use_limit))) as u32;
+        }
+    }
+}
+
+/// Class specific skills assigned to each class, used for the spoiler summary
+pub fn summarize(
+    skills_data: &CampingSkills,
+    options: &CampingOptions,
+) -> BTreeMap<String, Vec<String>> {
+    let mut summary: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for skill in skills_data.skills.iter().filter(|s| !options.is_shared(s)) {
+        for class in &skill.hero_classes {
+            summary
+                .entry(class.clone())
+                .or_default()
+                .push(skill.id.clone());
+        }
+    }
+    summary
 }
 
-pub fn write_to_file(skills_data: &Value, file_path: &PathBuf) -> Result<(), Box<dyn Error>> {
-    let file = File::create(file_path)?;
-    let buf_writer = BufWriter::new(file);
-    serde_json::to_writer_pretty(buf_writer, skills_data)?;
-    Ok(())
+/// Render the camping skill data as JSON for the mod
+pub fn render(skills_data: &CampingSkills) -> Result<String, Box<dyn Error>> {
+    Ok(serde_json::to_string_pretty(skills_data)?)
 }
 
 #[cfg(test)]
@@ -133,10 +368,14 @@ mod tests {
         });
 
         let test_seed_rng = seed::create_rng("testseed00");
-        let skills_data: Value = serde_json::from_str(test_input_json).unwrap();
-        let randomized_data = randomize(skills_data, test_seed_rng).unwrap();
+        let skills_data: CampingSkills = serde_json::from_str(test_input_json).unwrap();
+        let randomized_data =
+            randomize(skills_data, &CampingOptions::default(), test_seed_rng).unwrap();
 
-        assert_eq!(randomized_data, expected_output_json);
+        assert_eq!(
+            serde_json::to_value(randomized_data).unwrap(),
+            expected_output_json
+        );
     }
 
     #[test]
@@ -178,10 +417,14 @@ mod tests {
         });
 
         let test_seed_rng = seed::create_rng("testseed00");
-        let skills_data: Value = serde_json::from_str(test_input_json).unwrap();
-        let randomized_data = randomize(skills_data, test_seed_rng).unwrap();
+        let skills_data: CampingSkills = serde_json::from_str(test_input_json).unwrap();
+        let randomized_data =
+            randomize(skills_data, &CampingOptions::default(), test_seed_rng).unwrap();
 
-        assert_ne!(randomized_data, expected_output_json);
+        assert_ne!(
+            serde_json::to_value(randomized_data).unwrap(),
+            expected_output_json
+        );
     }
 
     #[test]
@@ -223,9 +466,158 @@ mod tests {
         });
 
         let test_seed_rng = seed::create_rng("testseed00");
-        let skills_data: Value = serde_json::from_str(test_input_json).unwrap();
-        let randomized_data = randomize(skills_data, test_seed_rng).unwrap();
+        let skills_data: CampingSkills = serde_json::from_str(test_input_json).unwrap();
+        let randomized_data =
+            randomize(skills_data, &CampingOptions::default(), test_seed_rng).unwrap();
 
-        assert_eq!(randomized_data, expected_output_json);
+        assert_eq!(
+            serde_json::to_value(randomized_data).unwrap(),
+            expected_output_json
+        );
+    }
+
+    #[test]
+    fn test_randomize_skills_keep_owner_count() {
+        let test_input_json = r#"
+            {
+                "skills": [
+                    {
+                        "id": "encourage",
+                        "hero_classes": ["class1", "class2", "class3"]
+                    },
+                    {
+                        "id": "skill1",
+                        "hero_classes": ["class1", "class2"]
+                    },
+                    {
+                        "id": "skill2",
+                        "hero_classes": ["class1", "class2", "class3"]
+                    }
+                ]
+            }
+        "#;
+
+        let options = CampingOptions {
+            keep_owner_count: true,
+            ..Default::default()
+        };
+        let test_seed_rng = seed::create_rng("testseed00");
+        let skills_data: CampingSkills = serde_json::from_str(test_input_json).unwrap();
+        let randomized_data = randomize(skills_data, &options, test_seed_rng).unwrap();
+
+        for (skill, expected_count) in [(1, 2), (2, 3)] {
+            let mut owners: Vec<&String> =
+                randomized_data.skills[skill].hero_classes.iter().collect();
+            owners.sort();
+            owners.dedup();
+            assert_eq!(owners.len(), expected_count);
+        }
+    }
+
+    #[test]
+    fn test_randomize_skills_min_per_class() {
+        let test_input_json = r#"
+            {
+                "skills": [
+                    {
+                        "id": "encourage",
+                        "hero_classes": ["class1", "class2", "class3"]
+                    },
+                    {
+                        "id": "skill1",
+                        "hero_classes": ["class1"]
+                    },
+                    {
+                        "id": "skill2",
+                        "hero_classes": ["class2"]
+                    },
+                    {
+                        "id": "skill3",
+                        "hero_classes": ["class3"]
+                    }
+                ]
+            }
+        "#;
+
+        let options = CampingOptions {
+            min_per_class: 1,
+            keep_owner_count: true,
+            ..Default::default()
+        };
+        let test_seed_rng = seed::create_rng("testseed00");
+        let skills_data: CampingSkills = serde_json::from_str(test_input_json).unwrap();
+        let randomized_data = randomize(skills_data, &options, test_seed_rng).unwrap();
+        let summary = summarize(&randomized_data, &options);
+
+        for class in ["class1", "class2", "class3"] {
+            assert_eq!(summary.get(class).map(|s| s.len()), Some(1));
+        }
+    }
+
+    #[test]
+    fn test_randomize_effects_and_costs() {
+        let test_input_json = r#"
+            {
+                "configuration": { "class_specific_number_of_classes_threshold": 1 },
+                "skills": [
+                    {
+                        "id": "encourage",
+                        "level": 0,
+                        "cost": 2,
+                        "use_limit": 1,
+                        "effects": [{ "selection": "individual", "type": "stress_heal_amount", "amount": 15 }],
+                        "hero_classes": ["class1", "class2", "class3"]
+                    },
+                    {
+                        "id": "skill1",
+                        "cost": 12,
+                        "use_limit": 1,
+                        "effects": [{ "selection": "self", "type": "buff", "sub_type": "campingDMG" }],
+                        "hero_classes": ["class1"]
+                    },
+                    {
+                        "id": "skill2",
+                        "cost": 1,
+                        "use_limit": 1,
+                        "effects": [{ "selection": "party_other", "type": "heal_amount", "amount": 5 }],
+                        "hero_classes": ["class2"]
+                    }
+                ]
+            }
+        "#;
+
+        let options = CampingOptions {
+            shuffle_effects: true,
+            cost_jitter: 3,
+            ..Default::default()
+        };
+        let test_seed_rng = seed::create_rng("testseed00");
+        let skills_data: CampingSkills = serde_json::from_str(test_input_json).unwrap();
+        let randomized_data = randomize(skills_data.clone(), &options, test_seed_rng).unwrap();
+
+        assert!(randomized_data.validate().is_ok());
+        // shared skills and unknown fields are untouched
+        assert_eq!(randomized_data.skills[0], skills_data.skills[0]);
+        assert_eq!(randomized_data.extra, skills_data.extra);
+        for skill in &randomized_data.skills[1..] {
+            assert!((1..=MAX_CAMP_TIME).contains(&skill.cost.unwrap()));
+            assert!((1..=MAX_USE_LIMIT).contains(&skill.use_limit.unwrap()));
+        }
+        // effects are traded as whole lists
+        let mut effect_types: Vec<&str> = randomized_data.skills[1..]
+            .iter()
+            .map(|s| s.effects[0].effect_type.as_str())
+            .collect();
+        effect_types.sort();
+        assert_eq!(effect_types, vec!["buff", "heal_amount"]);
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_cost() {
+        let skills_data: CampingSkills = serde_json::from_str(
+            r#"{ "skills": [{ "id": "skill1", "cost": 13, "hero_classes": ["class1"] }] }"#,
+        )
+        .unwrap();
+        assert!(skills_data.validate().is_err());
     }
 }
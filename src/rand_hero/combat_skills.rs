@@ -1,18 +1,29 @@
 use indexmap::IndexMap;
 use log::*;
-use rand::{Rng, rngs::StdRng};
-use regex::{Captures, Regex};
-use serde::Serialize;
-use std::collections::HashMap;
+use rand::Rng;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fs;
-use std::fs::{File, OpenOptions};
+use std::fs::File;
 use std::io::BufReader;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
 
+use crate::localization::StringTable;
+use crate::plan::ModPlan;
+use crate::seed::SeedRng;
+
 /// Translations for numeric positions to strings
-const POS_STR: &[&str] = &["one", "two", "three", "four", "five", "six", "seven"];
+/// Base game classes have seven skills, the extra positions are for modded classes with larger kits.
+const POS_STR: &[&str] = &[
+    "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven",
+    "twelve",
+];
+
+/// Optional file in a hero data directory declaring additional linked skill groups
+const LINKED_SKILLS_FILENAME: &str = "ddrand.linked_skills.json";
 
 /// Override mappings for heroes where icon order doesn't match file order.
 /// Returns None to use default file order, Some(pos) to override.
@@ -27,15 +38,95 @@ fn get_icon_position_override(hero: &str, skill: &str) -> Option<usize> {
     }
 }
 
+/// Built-in linked skill groups for base game and DLC classes.
+/// Declarations read from hero data directories replace these for the same class.
+fn default_linked_skill_groups() -> Vec<LinkedSkillGroup> {
+    vec![
+        // Abomination beast skills are REQUIRED to be together in a group.
+        // With all 7 skills selected at once the UI overflows and skill selection is disabled,
+        // adjust the applicable lines to allow selecting 4 skills like any other class.
+        LinkedSkillGroup {
+            class: String::from("abomination"),
+            skills: vec![
+                String::from("transform"),
+                String::from("rake"),
+                String::from("rage"),
+                String::from("slam"),
+            ],
+            patches: vec![
+                LinePatch {
+                    prefix: String::from("skill_selection"),
+                    replace: vec![
+                        (String::from("false"), String::from("true")),
+                        (String::from("7"), String::from("4")),
+                    ],
+                },
+                LinePatch {
+                    prefix: String::from("generation"),
+                    replace: vec![(String::from("7"), String::from("4"))],
+                },
+            ],
+        },
+    ]
+}
+
+/// Skills of a single class which must be assigned to the same hero, e.g. transform or stance mechanics
+#[derive(Debug, Clone, Deserialize)]
+pub struct LinkedSkillGroup {
+    class: String,
+    skills: Vec<String>,
+    /// Adjustments to the declaring class' hero file once its skills are randomized
+    #[serde(default)]
+    patches: Vec<LinePatch>,
+}
+
+/// Text replacements applied to hero data lines starting with the given prefix
+#[derive(Debug, Clone, Deserialize)]
+pub struct LinePatch {
+    prefix: String,
+    replace: Vec<(String, String)>,
+}
+
+impl LinkedSkillGroup {
+    fn contains(&self, skill: &Skill) -> bool {
+        skill.class == self.class && self.skills.contains(&skill.name)
+    }
+
+    /// Apply any matching patches to a line from the declaring class' hero file
+    fn patch_line(&self, line: &str) -> String {
+        let mut out_line = line.to_string();
+        for patch in &self.patches {
+            if line.starts_with(&patch.prefix) {
+                for (from, to) in &patch.replace {
+                    out_line = out_line.replace(from, to);
+                }
+            }
+        }
+        out_line
+    }
+}
+
 /// Data read from the various hero class files
 #[derive(Debug, Clone)]
 pub struct Hero {
-    name: String,
-    data: Vec<String>,
+    pub name: String,
+    pub data: Vec<String>,
     sknames: Vec<String>,
     skills: Vec<Skill>,
 }
 
+impl Hero {
+    /// Hero without any skills, these are read in separately from its data lines
+    pub fn new(name: &str, data: Vec<String>) -> Hero {
+        Hero {
+            name: name.to_string(),
+            data,
+            sknames: Vec::new(),
+            skills: Vec::new(),
+        }
+    }
+}
+
 /// Information for each skills read in from various hero classes
 #[derive(Debug, Clone, PartialEq)]
 pub struct Skill {
@@ -49,13 +140,15 @@ pub struct Skill {
 #[derive(Debug, Serialize)]
 pub struct SkillLocalization {
     class: String,
-    map: HashMap<String, String>,
+    /// Original skill name mapped to the class and name of the skill replacing it
+    map: HashMap<String, (String, String)>,
 }
 
+/// Display text of every combat skill in a single language, keyed by class and skill name
 #[derive(Debug)]
 pub struct Translation {
     lang: String,
-    map: HashMap<String, String>,
+    map: HashMap<(String, String), String>,
 }
 
 /// Helper function to get all files to extract hero data from
@@ -112,127 +205,210 @@ pub fn get_data_files(
     Ok(datafiles)
 }
 
+/// Collect the built-in linked skill groups along with any declared in the hero data directories
+pub fn get_linked_skill_groups(hero_paths: &HashMap<String, PathBuf>) -> Vec<LinkedSkillGroup> {
+    let mut linked_groups = default_linked_skill_groups();
+
+    // Sort hero paths by key for deterministic ordering (HashMap iteration is randomized)
+    let mut sorted_paths: Vec<_> = hero_paths.iter().collect();
+    sorted_paths.sort_by_key(|(k, _)| *k);
+
+    for (_, hdir) in sorted_paths {
+        let lpath = hdir.join(LINKED_SKILLS_FILENAME);
+        if !lpath.is_file() {
+            continue;
+        }
+        let declared: Result<Vec<LinkedSkillGroup>, Box<dyn Error>> = fs::read_to_string(&lpath)
+            .map_err(|e| e.into())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.into()));
+        match declared {
+            Ok(declared) => {
+                info!("Read linked skill groups from {}", &lpath.display());
+                // a class declaring its own groups replaces any built-in groups for it
+                linked_groups.retain(|lg| !declared.iter().any(|d| d.class == lg.class));
+                linked_groups.extend(declared);
+            }
+            Err(e) => {
+                warn!(
+                    "Unable to read linked skill groups from {}\nReason: {}",
+                    &lpath.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    linked_groups
+}
+
 /// Extract hero specific data from the appropriate files
 pub fn extract_data(datafiles: &[PathBuf]) -> Vec<Hero> {
     let mut heroes: Vec<Hero> = Vec::new();
-    let re: Regex = Regex::new(r#"id\s"(\w*)"\s(.*)"#).unwrap();
     for hpath in datafiles {
         let cname = hpath.file_stem().unwrap().to_str().unwrap();
         let cname: Vec<&str> = cname.split('.').collect();
 
-        // default empty Hero object which gets updated with proper data later
-        let mut hero: Hero = Hero {
-            name: String::from(cname[0]),
-            data: Vec::new(),
-            sknames: Vec::new(),
-            skills: Vec::new(),
-        };
-
         // files are not large but use a BufReader anyway for safety
         let cfile = File::open(hpath).unwrap();
         let mut buf: String = String::new();
         let mut reader: BufReader<File> = BufReader::new(cfile);
         reader.read_to_string(&mut buf).unwrap();
 
-        // temporary variable to hold skill data to avoid duplicates since each skill has multiple
-        // lines in the associated class info file
-        // collect the names as the key and then each skill level's data as an array for the value
-        let mut tmp_data: IndexMap<String, Vec<String>> = IndexMap::new();
-        for line in buf.lines() {
-            if line.starts_with("combat_skill") {
-                let caps: Captures = re.captures(line).unwrap();
-                let skill_name: String = caps[1].to_string();
-
-                // need to keep track of the original skill names for each class
-                hero.sknames.push(skill_name.clone());
-
-                // check if the skill name already exists in the map
-                // if so then copy the existing data, append the new line, and put it back
-                // otherwise add the new key and line
-                match tmp_data.get(&skill_name) {
-                    Some(pr) => {
-                        let mut data = pr.to_owned();
-                        data.push(caps[2].to_string());
-                        tmp_data.insert(skill_name, data);
-                    }
-                    None => {
-                        tmp_data.insert(skill_name, vec![caps[2].to_string()]);
-                    }
-                }
-            } else {
-                // for any non-combat skill related data just add it to its own attribute
-                hero.data.push(line.to_string());
-            }
-        }
-
-        hero.sknames.dedup();
+        heroes.push(parse_hero(cname[0], &buf));
+    }
 
-        if hero.sknames.len() != tmp_data.len() {
-            warn!(
-                "Skill count mismatch for skill names and data: {} != {}",
-                hero.sknames.len(),
-                tmp_data.len()
-            );
-        }
+    // return a list of all heros with their associated data
+    heroes
+}
 
-        // for each skill we read in create a new Skill object and assign it to the hero
-        for (idx, skill_name) in tmp_data.keys().enumerate() {
-            let skill = Skill {
-                pos: idx,
-                class: hero.name.clone(),
-                name: skill_name.to_string(),
-                data: tmp_data.get(skill_name).unwrap().to_owned(),
+/// Split a hero file into its skills and every other line
+/// The lines of a skill do not need to be next to each other, modded classes may interleave them.
+fn parse_hero(name: &str, content: &str) -> Hero {
+    let re: Regex = Regex::new(r#"^combat_skill:.*?id\s"(\w*)"\s(.*)"#).unwrap();
+
+    // default empty Hero object which gets updated with proper data later
+    let mut hero: Hero = Hero::new(name, Vec::new());
+
+    // temporary variable to hold skill data to avoid duplicates since each skill has multiple
+    // lines in the associated class info file
+    // collect the names as the key and then each skill level's data as an array for the value
+    let mut tmp_data: IndexMap<String, Vec<String>> = IndexMap::new();
+    for line in content.lines() {
+        if line.starts_with("combat_skill") {
+            let Some(caps) = re.captures(line) else {
+                warn!(
+                    "Unrecognized combat skill line for {}, keeping it as is: {}",
+                    name, line
+                );
+                hero.data.push(line.to_string());
+                continue;
             };
-
-            hero.skills.push(skill);
+            tmp_data
+                .entry(caps[1].to_string())
+                .or_default()
+                .push(caps[2].to_string());
+        } else {
+            // for any non-combat skill related data just add it to its own attribute
+            hero.data.push(line.to_string());
         }
+    }
 
-        heroes.push(hero);
+    // need to keep track of the original skill names for each class, in the order they first appear
+    hero.sknames = tmp_data.keys().cloned().collect();
+
+    // for each skill we read in create a new Skill object and assign it to the hero
+    for (idx, (skill_name, data)) in tmp_data.into_iter().enumerate() {
+        hero.skills.push(Skill {
+            pos: idx,
+            class: hero.name.clone(),
+            name: skill_name,
+            data,
+        });
     }
 
-    // return a list of all heros with their associated data
-    heroes
+    hero
 }
 
 /// Render localization strings template
+/// One string table is rendered for each language, returned as pairs of the language and its XML.
 pub fn render_localizations(
-    translation: Translation,
+    translations: Vec<Translation>,
     cmap: Vec<SkillLocalization>,
-) -> Result<String, Box<dyn Error>> {
-    // header information for properly structured XML used by the game for the mod strings
-    let mut rendered = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<root>".to_string();
-    rendered = format!("{}\n<language id=\"{}\">", rendered, translation.lang);
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let english = translations.iter().find(|t| t.lang == "english");
+    let mut rendered_tables: Vec<(String, String)> = Vec::new();
+    for translation in &translations {
+        let table = build_localization(translation, english, &cmap);
+        rendered_tables.push((translation.lang.clone(), table.render()));
+    }
+
+    Ok(rendered_tables)
+}
+
+/// Build the mod string table for a single language
+fn build_localization(
+    translation: &Translation,
+    fallback: Option<&Translation>,
+    cmap: &[SkillLocalization],
+) -> StringTable {
+    let mut table = StringTable::default();
 
     // loop through the randomized skill map and lookup the proper display text for the new skills
-    // the default skill name is used as a key for the lookup in the translation table
+    // the class and name of the new skill are used as a key for the lookup in the translation table
     for sk in cmap {
-        for (old, new) in sk.map {
-            rendered = format!(
-                "{}\n<entry id=\"combat_skill_name_{}_{}\"><![CDATA[{}]]></entry>",
-                rendered, &sk.class, old, &translation.map[&new]
+        for (old, new) in &sk.map {
+            // not every language or modded hero provides strings for every skill,
+            // fall back to the English text and then to the skill id
+            let display = match translation
+                .map
+                .get(new)
+                .or_else(|| fallback.and_then(|f| f.map.get(new)))
+            {
+                Some(display) => display,
+                None => {
+                    warn!(
+                        "No {} display text found for skill '{}' of {}, using its id",
+                        &translation.lang, &new.1, &new.0
+                    );
+                    &new.1
+                }
+            };
+            table.insert(
+                &translation.lang,
+                &format!("combat_skill_name_{}_{}", &sk.class, old),
+                display,
             );
-            rendered = format!(
-                "{}\n<entry id=\"upgrade_tree_name_{}.{}\"><![CDATA[{}]]></entry>",
-                rendered, &sk.class, old, &translation.map[&new]
+            table.insert(
+                &translation.lang,
+                &format!("upgrade_tree_name_{}.{}", &sk.class, old),
+                display,
             );
         }
     }
 
-    // close the language and root tags to finalize the XML structure
-    rendered = format!("{}\n</language></root>", rendered);
+    table
+}
 
-    Ok(rendered)
+/// Add the hero files to the mod with each hero keeping its own skills
+/// Used when only other hero data, such as stats, is randomized.
+pub fn write_data(plan: &mut ModPlan, mod_hpath: &Path, heroes: &[Hero]) {
+    for hero in heroes {
+        let hpath = mod_hpath
+            .join(&hero.name)
+            .join(format!("{}.info.darkest", &hero.name));
+        let mut content = String::new();
+
+        for line in &hero.data {
+            content.push_str(&format!("{}\n", line));
+        }
+        for skill in &hero.skills {
+            for line in &skill.data {
+                content.push_str(&format!("combat_skill: .id \"{}\" {}\n", skill.name, line));
+            }
+        }
+
+        plan.add_file(hpath, content);
+    }
 }
 
-/// Randomize the hero skills and write the appropriate files to the mod directory
+/// Randomize the hero skills and add the appropriate files to the mod
 pub fn randomize(
+    plan: &mut ModPlan,
     base_hpaths: &HashMap<String, PathBuf>,
     mod_hpath: &Path,
     heroes: Vec<Hero>,
-    rng: StdRng,
+    linked_groups: &[LinkedSkillGroup],
+    rng: SeedRng,
 ) -> Vec<SkillLocalization> {
     info!("Randomizing skills");
-    let mut seed_rng: StdRng = rng;
+    let mut seed_rng: SeedRng = rng;
+
+    // only groups declared for classes being randomized are relevant
+    let linked_groups: Vec<LinkedSkillGroup> = linked_groups
+        .iter()
+        .filter(|lg| heroes.iter().any(|h| h.name == lg.class))
+        .cloned()
+        .collect();
 
     // master collection holding all of the skills for all hero classes
     let mut skill_collection: Vec<Skill> = Vec::new();
@@ -243,56 +419,56 @@ pub fn randomize(
     }
 
     // shuffle the skills into smaller groups to ease the randomization process
-    let mut skill_groups = shuffle_skills(skill_collection, heroes.len(), &mut seed_rng);
+    // each group matches the skill count of the hero at the same index
+    let group_sizes: Vec<usize> = heroes.iter().map(|h| h.skills.len()).collect();
+    let skill_groups = shuffle_skills(
+        skill_collection,
+        &group_sizes,
+        &linked_groups,
+        &mut seed_rng,
+    );
     let mut skloc: Vec<SkillLocalization> = Vec::new();
 
-    for hero in heroes {
+    for (hero, hgroup) in heroes.into_iter().zip(skill_groups) {
+        if hgroup.len() != hero.sknames.len() {
+            warn!(
+                "Skill count mismatch for {}: {} != {}, leaving its skills unchanged",
+                &hero.name,
+                hgroup.len(),
+                hero.sknames.len()
+            );
+            continue;
+        }
         let hdir = Path::join(mod_hpath, Path::new(&hero.name));
         let hpath = Path::join(&hdir, Path::new(&format!("{}.info.darkest", &hero.name)));
-        fs::create_dir_all(hdir).unwrap();
-        let mut of = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(hpath)
-            .unwrap();
+        let mut content = String::new();
 
         for line in &hero.data {
-            // beast skills mean all 7 skills are selected at once causing a ui overflow and disables
-            // skill selection, adjust the applicable lines to solve this
-            let out_line = if &hero.name == "abomination" && line.starts_with("skill_selection") {
-                line.replace("false", "true").replace('7', "4")
-            } else if &hero.name == "abomination" && line.starts_with("generation") {
-                line.replace('7', "4")
-            } else {
-                // ignore lines which are not applicable and write them out as is to the new hero file
-                line.to_string()
-            };
-            of.write_fmt(format_args!("{}\n", out_line))
-                .expect("could not write hero data");
+            // classes declaring linked skills may need their hero file adjusted once their skills
+            // are split up, all other lines are written out as is to the new hero file
+            let mut out_line = line.to_string();
+            for lg in linked_groups.iter().filter(|lg| lg.class == hero.name) {
+                out_line = lg.patch_line(&out_line);
+            }
+            content.push_str(&format!("{}\n", out_line));
         }
 
         // map of default skill names to their new randomized value
         // needed as the game requires each hero to have the proper keys in the files otherwise
         // it cannot properly render skill names
-        let mut align: HashMap<String, String> = HashMap::new();
-
-        // pick a skill group randomly for the current hero and write the new skill data
-        // remove the chosen groups to avoid duplicates being assigned
-        let gidx = seed_rng.random_range(0..skill_groups.len());
-        let hgroup = &skill_groups[gidx].clone();
-        skill_groups.remove(gidx);
+        let mut align: HashMap<String, (String, String)> = HashMap::new();
 
         for (idx, hsname) in hero.sknames.iter().enumerate() {
             // update the skill alignment map and then write the skill data to the mod hero file
-            align.insert(hsname.to_string(), hgroup[idx].name.clone());
+            align.insert(
+                hsname.to_string(),
+                (hgroup[idx].class.clone(), hgroup[idx].name.clone()),
+            );
             for line in &hgroup[idx].data {
-                of.write_fmt(format_args!(
-                    r#"combat_skill: .id "{}" {}"#,
+                content.push_str(&format!(
+                    "combat_skill: .id \"{}\" {}\n",
                     hero.sknames[idx], line
-                ))
-                .unwrap();
-                of.write_all(b"\n").unwrap();
+                ));
             }
 
             // copy skills icons for the randomized skills to the appropriate hero for in game alignment
@@ -300,16 +476,22 @@ pub fn randomize(
             // use override position if available for source skill, otherwise use file order
             let sk_pos =
                 get_icon_position_override(sk_class, &hgroup[idx].name).unwrap_or(hgroup[idx].pos);
-            let from_fname = format!("{}.ability.{}.png", &sk_class, POS_STR[sk_pos]);
-            let from_path = Path::join(base_hpaths.get(sk_class).unwrap(), Path::new(&from_fname));
             // use override position if available for target skill slot, otherwise use file order
             let target_pos = get_icon_position_override(&hero.name, hsname).unwrap_or(idx);
+            let (Some(sk_pos_str), Some(target_pos_str)) =
+                (POS_STR.get(sk_pos), POS_STR.get(target_pos))
+            else {
+                warn!(
+                    "No icon name for skill position of {} {}, skipping icon",
+                    &hero.name, hsname
+                );
+                continue;
+            };
+            let from_fname = format!("{}.ability.{}.png", &sk_class, sk_pos_str);
+            let from_path = Path::join(base_hpaths.get(sk_class).unwrap(), Path::new(&from_fname));
             let to_fname: PathBuf = vec![
                 Path::new(&hero.name),
-                Path::new(&format!(
-                    "{}.ability.{}.png",
-                    &hero.name, POS_STR[target_pos]
-                )),
+                Path::new(&format!("{}.ability.{}.png", &hero.name, target_pos_str)),
             ]
             .into_iter()
             .collect();
@@ -324,8 +506,9 @@ pub fn randomize(
             );
             debug!("{:?} {:?}", &to_fname, &from_fname);
 
-            fs::copy(&from_path, &to_path).unwrap();
+            plan.add_copy(to_path, from_path);
         }
+        plan.add_file(hpath, content);
 
         // build the SkillLocalization object which is used to template the mod skill names
         skloc.push(SkillLocalization {
@@ -337,12 +520,27 @@ pub fn randomize(
     skloc
 }
 
-/// Read localization strings from default game data
-pub fn extract_localizations(install_dir: &Path) -> Result<Translation, Box<dyn Error>> {
-    let mut translation = Translation {
-        lang: String::from("english"),
-        map: HashMap::new(),
-    };
+/// Skills assigned to each class as `class.skill` of their original owner, used for the spoiler summary
+pub fn summarize(skloc: &[SkillLocalization]) -> BTreeMap<String, Vec<String>> {
+    skloc
+        .iter()
+        .map(|loc| {
+            let mut skills: Vec<String> = loc
+                .map
+                .values()
+                .map(|(class, name)| format!("{}.{}", class, name))
+                .collect();
+            skills.sort();
+            (loc.class.clone(), skills)
+        })
+        .collect()
+}
+
+/// Read localization strings from default game data and any mods providing randomized heroes
+pub fn extract_localizations(
+    install_dir: &Path,
+    hero_paths: &HashMap<String, PathBuf>,
+) -> Result<Vec<Translation>, Box<dyn Error>> {
     let mut lfiles: Vec<PathBuf> = Vec::new();
 
     // build paths to default string tables including dlc
@@ -368,93 +566,128 @@ pub fn extract_localizations(install_dir: &Path) -> Result<Translation, Box<dyn
         }
     }
 
-    // since only English is supported for the moment search for it explicitly
-    let re_lang = Regex::new(r#"\s+<language id="english">(?s)(.*)</language>"#)?;
-    let re_map = Regex::new(r#"<entry id="combat_skill_name_(.*)"><!\[CDATA\[(.*)\]\]></entry>"#)?;
+    // heroes from other mods are found at `<mod>/heroes/<class>` and keep their strings in `<mod>/localization`
+    let mut mod_ldirs: Vec<PathBuf> = hero_paths
+        .values()
+        .filter(|hdir| !hdir.starts_with(install_dir) || hdir.starts_with(install_dir.join("mods")))
+        .filter_map(|hdir| hdir.parent().and_then(|p| p.parent()))
+        .map(|mod_root| mod_root.join("localization"))
+        .collect();
+    mod_ldirs.sort();
+    mod_ldirs.dedup();
+    for ldir in mod_ldirs {
+        if let Ok(dir_read) = fs::read_dir(&ldir) {
+            let mut mod_lfiles: Vec<PathBuf> = dir_read
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|p| p.is_file() && p.to_string_lossy().ends_with(".string_table.xml"))
+                .collect();
+            mod_lfiles.sort();
+            lfiles.extend(mod_lfiles);
+        }
+    }
 
+    let mut strings = StringTable::default();
     for file in lfiles {
-        let content = fs::read_to_string(file)?;
-
-        // capture all English language data from valid string table files
-        if let Some(lcaps) = re_lang.captures(&content) {
-            let entries = lcaps[1].to_string();
-            let entries = entries.split("\r\n");
-
-            for entry in entries {
-                // because the xml is processed by regex just stop processing when the closing language tag is reached
-                // this can be removed when proper multi language support is added
-                if entry.contains("</language>") {
-                    break;
-                }
-                if let Some(line) = re_map.captures(entry) {
-                    let name = line[1].to_string();
-                    if name.contains("level") {
-                        continue;
-                    }
+        let content = fs::read_to_string(&file)?;
+        match StringTable::parse(&content) {
+            Ok(table) => strings.merge(table),
+            Err(e) => warn!(
+                "Unable to parse string table {}\nReason: {}",
+                &file.display(),
+                e
+            ),
+        }
+    }
 
-                    // set the correct position in the strings for the actual skill names
-                    // the underscores in space separated class names cause splitting issues by default
-                    let sp_pos = if name.contains("man_at_arms") {
-                        3
-                    } else if name.contains("grave_robber")
-                        || name.contains("plague_doctor")
-                        || name.contains("bounty_hunter")
-                    {
-                        2
-                    } else {
-                        1
-                    };
-
-                    // map the original skill name to its displayed text
-                    let sk_name = name.split('_').collect::<Vec<&str>>()[sp_pos..].join("_");
-                    let sk_value = line[2].to_string();
-
-                    if sk_value != "Move" {
-                        translation.map.insert(sk_name, sk_value);
-                    }
-                }
+    // only combat skill names of known classes are needed, the class list is used to split the
+    // entry ids as class names themselves can contain underscores
+    let classes: Vec<&String> = hero_paths.keys().collect();
+    let mut translations: Vec<Translation> = Vec::new();
+    for (lang, entries) in strings.languages {
+        let mut translation = Translation {
+            lang,
+            map: HashMap::new(),
+        };
+        for (id, text) in entries {
+            let Some((class, sk_name)) = resolve_skill_id(&id, &classes) else {
+                continue;
+            };
+            if sk_name.contains("level") || text == "Move" {
+                continue;
             }
+            translation.map.insert((class, sk_name), text);
         }
+        translations.push(translation);
     }
 
-    Ok(translation)
+    Ok(translations)
+}
+
+/// Split a `combat_skill_name_<class>_<skill>` string id into its class and skill name
+/// The longest matching class wins so a class name that is a prefix of another cannot match first.
+fn resolve_skill_id(id: &str, classes: &[&String]) -> Option<(String, String)> {
+    let rest = id.strip_prefix("combat_skill_name_")?;
+    classes
+        .iter()
+        .filter_map(|class| {
+            rest.strip_prefix(class.as_str())
+                .and_then(|r| r.strip_prefix('_'))
+                .filter(|sk_name| !sk_name.is_empty())
+                .map(|sk_name| (class.to_string(), sk_name.to_string()))
+        })
+        .max_by_key(|(class, _)| class.len())
 }
 
-/// Shuffle the full skill list into smaller groups
+/// Shuffle the full skill list into smaller groups, one for each of the requested group sizes
+/// Skills belonging to a linked skill group are always placed together in the same group.
 fn shuffle_skills(
     skill_collection: Vec<Skill>,
-    group_count: usize,
-    seed_rng: &mut StdRng,
+    group_sizes: &[usize],
+    linked_groups: &[LinkedSkillGroup],
+    seed_rng: &mut SeedRng,
 ) -> Vec<Vec<Skill>> {
     // master collection holding all of the skills for all hero classes
     let mut skill_collection: Vec<Skill> = skill_collection;
-    let mut skill_groups: Vec<Vec<Skill>> = Vec::new();
-
-    // Abomination beast skills are REQUIRED to be together in a group, define them here
-    let beast_skills = [
-        "transform".to_string(),
-        "rake".to_string(),
-        "rage".to_string(),
-        "slam".to_string(),
-    ];
-    for hero_idx in 0..group_count {
-        let mut group: Vec<Skill> = Vec::new();
-        // each group should contain 8 skills total, max number is a variable for safety
-        while group.len() < beast_skills.len() + 3 {
+    let mut skill_groups: Vec<Vec<Skill>> = vec![Vec::new(); group_sizes.len()];
+
+    // place each linked group as a whole first, otherwise the groups could fill up and leave
+    // no single group with enough room to keep the linked skills together
+    for lg in linked_groups {
+        let (mut linked, rest): (Vec<Skill>, Vec<Skill>) =
+            skill_collection.into_iter().partition(|sk| lg.contains(sk));
+        skill_collection = rest;
+        if linked.is_empty() {
+            continue;
+        }
+
+        let available: Vec<usize> = (0..group_sizes.len())
+            .filter(|gidx| skill_groups[*gidx].len() + linked.len() <= group_sizes[*gidx])
+            .collect();
+        if available.is_empty() {
+            warn!(
+                "No skill group has room for the linked {} skills, they will not be randomized together",
+                &lg.class
+            );
+            skill_collection.append(&mut linked);
+            continue;
+        }
+        let gidx = available[seed_rng.random_range(0..available.len())];
+        skill_groups[gidx].append(&mut linked);
+    }
+
+    for (group, group_size) in skill_groups.iter_mut().zip(group_sizes) {
+        while group.len() < *group_size && !skill_collection.is_empty() {
             let rand_idx = seed_rng.random_range(0..skill_collection.len());
-            let skname = &skill_collection[rand_idx].name;
-            // skip beast skills here as they are assigned to the final group to keep them together
-            if !beast_skills.contains(skname) {
-                group.push(skill_collection[rand_idx].clone());
-                skill_collection.remove(rand_idx);
-            }
-            // stop on the last group and just add the remaining skills to end the loop
-            // this should simply be the beast skills that were ignored earlier
-            if hero_idx == group_count - 1 {
-                group.append(&mut skill_collection);
-            }
+            group.push(skill_collection.remove(rand_idx));
         }
-        skill_groups.push(group);
+    }
+
+    if !skill_collection.is_empty() {
+        warn!(
+            "{} skills did not fit in any skill group and were not assigned",
+            skill_collection.len()
+        );
     }
 
     skill_groups
@@ -463,6 +696,7 @@ fn shuffle_skills(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::seed;
 
     #[test]
     fn test_get_icon_position_override_override() {
@@ -481,4 +715,126 @@ mod tests {
         assert_eq!(get_icon_position_override("hero1", "mace_bash"), None);
         assert_eq!(get_icon_position_override("vestal", "nonexistent"), None);
     }
+
+    #[test]
+    fn test_parse_hero_interleaved_skills() {
+        let hero = parse_hero(
+            "modded",
+            r#"resistances: .stun 40%
+combat_skill: .id "strike" .level 0 .atk 90%
+combat_skill: .id "guard" .level 0 .heal 2 3
+combat_skill: .id "strike" .level 1 .atk 95%
+combat_skill_foo: .id "custom"
+combat_skill: .id "guard" .level 1 .heal 3 4
+weapon: .name "modded_weapon_0""#,
+        );
+        assert_eq!(hero.sknames, vec!["strike", "guard"]);
+        assert_eq!(hero.skills.len(), 2);
+        assert_eq!(
+            hero.skills[0].data,
+            vec![".level 0 .atk 90%", ".level 1 .atk 95%"]
+        );
+        assert_eq!(hero.skills[1].pos, 1);
+        assert_eq!(
+            hero.data,
+            vec![
+                "resistances: .stun 40%",
+                r#"combat_skill_foo: .id "custom""#,
+                r#"weapon: .name "modded_weapon_0""#
+            ]
+        );
+    }
+
+    fn test_skills(class: &str, names: &[&str]) -> Vec<Skill> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(pos, name)| Skill {
+                pos,
+                class: class.to_string(),
+                name: name.to_string(),
+                data: Vec::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_shuffle_skills_keeps_linked_group_together() {
+        let mut skills = test_skills("class1", &["a", "b", "c", "d"]);
+        skills.extend(test_skills("class2", &["a", "e", "f", "g"]));
+        skills.extend(test_skills("class3", &["h", "i", "j", "k"]));
+        let linked = vec![LinkedSkillGroup {
+            class: String::from("class2"),
+            skills: vec![String::from("e"), String::from("f"), String::from("g")],
+            patches: Vec::new(),
+        }];
+
+        let mut test_seed_rng = seed::create_rng("testseed00");
+        let groups = shuffle_skills(skills, &[4, 4, 4], &linked, &mut test_seed_rng);
+
+        assert_eq!(groups.len(), 3);
+        assert!(groups.iter().all(|g| g.len() == 4));
+        let linked_counts: Vec<usize> = groups
+            .iter()
+            .map(|g| g.iter().filter(|sk| linked[0].contains(sk)).count())
+            .collect();
+        assert!(linked_counts.contains(&3));
+        assert!(linked_counts.iter().all(|c| *c == 0 || *c == 3));
+    }
+
+    #[test]
+    fn test_shuffle_skills_variable_group_sizes() {
+        let mut skills = test_skills("class1", &["a", "b", "c", "d", "e", "f"]);
+        skills.extend(test_skills(
+            "class2",
+            &["a", "b", "c", "d", "e", "f", "g", "h"],
+        ));
+        skills.extend(test_skills("class3", &["a", "b", "c", "d", "e", "f", "g"]));
+
+        let mut test_seed_rng = seed::create_rng("testseed00");
+        let groups = shuffle_skills(skills, &[6, 8, 7], &[], &mut test_seed_rng);
+
+        let sizes: Vec<usize> = groups.iter().map(|g| g.len()).collect();
+        assert_eq!(sizes, vec![6, 8, 7]);
+    }
+
+    #[test]
+    fn test_linked_skill_group_patch_line() {
+        let abomination = &default_linked_skill_groups()[0];
+        assert_eq!(
+            abomination.patch_line(
+                "skill_selection: .can_select_combat_skills false .number_of_selected_combat_skills_max 7"
+            ),
+            "skill_selection: .can_select_combat_skills true .number_of_selected_combat_skills_max 4"
+        );
+        assert_eq!(
+            abomination.patch_line("tag: .id \"religious\""),
+            "tag: .id \"religious\""
+        );
+    }
+
+    #[test]
+    fn test_resolve_skill_id() {
+        let man = String::from("man");
+        let man_at_arms = String::from("man_at_arms");
+        let crusader = String::from("crusader");
+        let classes = vec![&man, &man_at_arms, &crusader];
+
+        assert_eq!(
+            resolve_skill_id("combat_skill_name_man_at_arms_crush", &classes),
+            Some((String::from("man_at_arms"), String::from("crush")))
+        );
+        assert_eq!(
+            resolve_skill_id("combat_skill_name_crusader_holy_lance", &classes),
+            Some((String::from("crusader"), String::from("holy_lance")))
+        );
+        assert_eq!(
+            resolve_skill_id("combat_skill_name_vestal_judgement", &classes),
+            None
+        );
+        assert_eq!(
+            resolve_skill_id("upgrade_tree_name_crusader.smite", &classes),
+            None
+        );
+    }
 }
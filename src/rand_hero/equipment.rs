@@ -0,0 +1,403 @@
+use log::*;
+use rand::{Rng, seq::SliceRandom};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::darkest::{Entry, Number};
+use crate::rand_hero::combat_skills::Hero;
+use crate::seed::SeedRng;
+
+/// Hero file keys holding one line per upgrade tier
+const EQUIPMENT_KEYS: &[&str] = &["weapon", "armour"];
+
+/// Number of upgrades in each class's equipment upgrade tree, keyed by tree id such as `crusader.weapon`
+pub type UpgradeLevels = BTreeMap<String, usize>;
+
+/// How weapon and armour tiers are randomized
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EquipmentMode {
+    /// Trade whole weapon and armour progressions between classes
+    Swap,
+    /// Adjust the stats of each tier within the variance
+    Perturb,
+}
+
+/// Options for weapon and armour tier randomization
+#[derive(Debug, Clone)]
+pub struct EquipmentOptions {
+    pub mode: EquipmentMode,
+    /// Maximum change to any tier stat as a percentage when perturbing
+    pub variance: u32,
+}
+
+/// Read the weapon and armour upgrade trees from the `upgrades/heroes` directories
+/// Missing directories are skipped as modded heroes do not always ship upgrade trees.
+pub fn read_upgrade_levels(upgrade_dirs: &[PathBuf]) -> UpgradeLevels {
+    let mut levels = UpgradeLevels::new();
+    for upgrade_dir in upgrade_dirs {
+        let Ok(dir_read) = fs::read_dir(upgrade_dir) else {
+            continue;
+        };
+        let mut upgrade_files: Vec<PathBuf> = dir_read
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.to_string_lossy().ends_with(".upgrades.json"))
+            .collect();
+        // Sort for consistent results, directory listings have no fixed order
+        upgrade_files.sort();
+
+        for upgrade_file in upgrade_files {
+            match fs::read_to_string(&upgrade_file)
+                .map_err(|e| e.to_string())
+                .and_then(|content| {
+                    serde_json::from_str::<Value>(&content).map_err(|e| e.to_string())
+                }) {
+                Ok(data) => levels.extend(equipment_trees(&data)),
+                Err(e) => warn!(
+                    "Unable to read upgrade trees from {}\nReason: {}",
+                    upgrade_file.display(),
+                    e
+                ),
+            }
+        }
+    }
+    levels
+}
+
+/// Randomize the weapon and armour tiers of each hero
+/// Tier names are always kept so the upgrade trees and localization of each class stay valid, and a
+/// class is only randomized when its tier lines match the levels of its upgrade tree.
+pub fn randomize(
+    heroes: Vec<Hero>,
+    levels: &UpgradeLevels,
+    options: &EquipmentOptions,
+    rng: SeedRng,
+) -> Vec<Hero> {
+    info!("Randomizing hero weapons and armour");
+    let mut seed_rng: SeedRng = rng;
+
+    match options.mode {
+        EquipmentMode::Swap => swap_tiers(heroes, levels, &mut seed_rng),
+        EquipmentMode::Perturb => {
+            let variance = f64::from(options.variance) / 100.0;
+            heroes
+                .into_iter()
+                .map(|hero| perturb_tiers(hero, levels, variance, &mut seed_rng))
+                .collect()
+        }
+    }
+}
+
+/// Shuffle the weapon and armour progressions between classes
+/// Progressions are only traded between classes with the same number of upgrade levels.
+fn swap_tiers(mut heroes: Vec<Hero>, levels: &UpgradeLevels, seed_rng: &mut SeedRng) -> Vec<Hero> {
+    for key in EQUIPMENT_KEYS {
+        // group the heroes by tier count, using a sorted map for consistent output with the same seed
+        let mut by_tier_count: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (hidx, hero) in heroes.iter().enumerate() {
+            if let Some(tier_count) = tier_count(hero, key, levels) {
+                by_tier_count.entry(tier_count).or_default().push(hidx);
+            }
+        }
+
+        for hidxs in by_tier_count.values() {
+            let mut donors = hidxs.clone();
+            donors.shuffle(seed_rng);
+            let progressions: Vec<Vec<Entry>> = donors
+                .iter()
+                .map(|didx| tier_entries(&heroes[*didx], key))
+                .collect();
+
+            for (hidx, (didx, progression)) in hidxs.iter().zip(donors.iter().zip(progressions)) {
+                debug!(
+                    "{} {} tiers from {}",
+                    &heroes[*hidx].name, key, &heroes[*didx].name
+                );
+                let mut donor_tiers = progression.into_iter();
+                for line in heroes[*hidx].data.iter_mut() {
+                    let Some(entry) = Entry::parse(line).filter(|e| e.key == *key) else {
+                        continue;
+                    };
+                    if let Some(mut donor) = donor_tiers.next() {
+                        // keep the recipient's tier name which the upgrade trees refer to
+                        if let (Some(name), Some(donor_name)) =
+                            (entry.get("name"), donor.get_mut("name"))
+                        {
+                            *donor_name = name.to_vec();
+                        }
+                        *line = donor.to_string();
+                    }
+                }
+            }
+        }
+    }
+
+    heroes
+}
+
+/// Adjust each numeric tier stat within the variance
+/// Every stat keeps the direction of its base game step from the previous tier, so upgrading is
+/// never a downgrade, and a stat which was not zero never becomes zero or changes sign.
+fn perturb_tiers(
+    mut hero: Hero,
+    levels: &UpgradeLevels,
+    variance: f64,
+    seed_rng: &mut SeedRng,
+) -> Hero {
+    for key in EQUIPMENT_KEYS {
+        if tier_count(&hero, key, levels).is_none() {
+            continue;
+        }
+        // previous tier as it is in the base game and after perturbing
+        let mut previous: Option<(Entry, Entry)> = None;
+        for line in hero.data.iter_mut() {
+            let Some(original) = Entry::parse(line).filter(|e| e.key == *key) else {
+                continue;
+            };
+            let mut entry = original.clone();
+            for (name, values) in entry.props.iter_mut() {
+                if name == "name" {
+                    continue;
+                }
+                for (vidx, value) in values.iter_mut().enumerate() {
+                    let Some(mut number) = Number::parse(value) else {
+                        continue;
+                    };
+                    let factor = 1.0 + seed_rng.random_range(-variance..=variance);
+                    let base = number.value;
+                    let unit = if base.fract() == 0.0 { 1.0 } else { 0.01 };
+                    let mut perturbed = round_to(base * factor, unit);
+
+                    // a stat that was not zero keeps its sign and is never rounded away
+                    if base > 0.0 {
+                        perturbed = perturbed.max(unit);
+                    } else if base < 0.0 {
+                        perturbed = perturbed.min(-unit);
+                    }
+                    let previous_value = |tier: &Entry| {
+                        tier.get(name)
+                            .and_then(|pvalues| pvalues.get(vidx))
+                            .and_then(|pvalue| Number::parse(pvalue))
+                            .map(|pnumber| pnumber.value)
+                    };
+                    if let Some((prev_original, prev_perturbed)) = &previous
+                        && let (Some(prev_base), Some(prev_value)) = (
+                            previous_value(prev_original),
+                            previous_value(prev_perturbed),
+                        )
+                    {
+                        perturbed = if base > prev_base {
+                            perturbed.max(prev_value + unit)
+                        } else if base < prev_base {
+                            perturbed.min(prev_value - unit)
+                        } else {
+                            prev_value
+                        };
+                    }
+                    number.value = round_to(perturbed, unit);
+                    *value = number.to_string();
+                }
+            }
+            *line = entry.to_string();
+            previous = Some((original, entry));
+        }
+    }
+
+    hero
+}
+
+/// Tier count of the hero's equipment when it matches the upgrade tree, `None` when the class has
+/// no tiers or its tiers do not line up with the tree
+/// Classes without an upgrade tree, such as some modded heroes, fall back to their tier lines.
+fn tier_count(hero: &Hero, key: &str, levels: &UpgradeLevels) -> Option<usize> {
+    let tier_count = tier_entries(hero, key).len();
+    if tier_count == 0 {
+        return None;
+    }
+    match levels.get(&format!("{}.{}", &hero.name, key)) {
+        // the base tier needs no upgrade so there is one tier more than upgrade levels
+        Some(upgrades) if upgrades + 1 != tier_count => {
+            warn!(
+                "{} {} has {} tiers but {} upgrades, leaving it unchanged",
+                &hero.name, key, tier_count, upgrades
+            );
+            None
+        }
+        Some(_) => Some(tier_count),
+        None => {
+            debug!("No upgrade tree for {} {}", &hero.name, key);
+            Some(tier_count)
+        }
+    }
+}
+
+/// Weapon and armour trees of an upgrades file with their number of upgrade levels
+fn equipment_trees(data: &Value) -> UpgradeLevels {
+    data.get("trees")
+        .and_then(|trees| trees.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|tree| {
+            let id = tree.get("id")?.as_str()?;
+            let requirements = tree.get("requirements")?.as_array()?;
+            EQUIPMENT_KEYS
+                .iter()
+                .any(|key| id.ends_with(&format!(".{}", key)))
+                .then(|| (id.to_string(), requirements.len()))
+        })
+        .collect()
+}
+
+/// All tier lines with the given key in file order
+fn tier_entries(hero: &Hero, key: &str) -> Vec<Entry> {
+    hero.data
+        .iter()
+        .filter_map(|line| Entry::parse(line))
+        .filter(|entry| entry.key == key)
+        .collect()
+}
+
+/// Whole numbers stay whole, anything else is kept to two decimals
+fn round_to(value: f64, unit: f64) -> f64 {
+    if unit >= 1.0 {
+        value.round()
+    } else {
+        (value * 100.0).round() / 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed;
+
+    fn test_hero(name: &str, weapons: &[(u32, u32)]) -> Hero {
+        let mut data = vec![format!("resistances: .stun 40% .bleed 30%")];
+        for (tier, (dmg, spd)) in weapons.iter().enumerate() {
+            data.push(format!(
+                r#"weapon: .name "{}_weapon_{}" .atk 0% .dmg {} {} .crit 0% .spd {}"#,
+                name,
+                tier,
+                dmg,
+                dmg * 2,
+                spd
+            ));
+        }
+        Hero::new(name, data)
+    }
+
+    fn weapon_values(hero: &Hero, prop: &str) -> Vec<Vec<f64>> {
+        tier_entries(hero, "weapon")
+            .iter()
+            .map(|entry| {
+                entry
+                    .get(prop)
+                    .unwrap()
+                    .iter()
+                    .map(|v| Number::parse(v).unwrap().value)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_swap_keeps_names_and_tree_levels() {
+        let heroes = vec![
+            test_hero("crusader", &[(6, 1), (7, 1), (8, 2)]),
+            test_hero("vestal", &[(4, 0), (5, 0), (6, 1)]),
+            test_hero("jester", &[(5, 7), (6, 7), (7, 8)]),
+        ];
+        // the jester's tree does not match its tiers so it keeps its own weapons
+        let levels = UpgradeLevels::from([
+            (String::from("crusader.weapon"), 2),
+            (String::from("vestal.weapon"), 2),
+            (String::from("jester.weapon"), 4),
+        ]);
+        let options = EquipmentOptions {
+            mode: EquipmentMode::Swap,
+            variance: 0,
+        };
+
+        let mut swapped = heroes.clone();
+        for seed_val in ["testseed00", "testseed01", "testseed02", "testseed03"] {
+            swapped = randomize(
+                heroes.clone(),
+                &levels,
+                &options,
+                seed::create_rng(seed_val),
+            );
+            if swapped[0].data != heroes[0].data {
+                break;
+            }
+        }
+        assert_ne!(swapped[0].data, heroes[0].data);
+        assert_eq!(
+            weapon_values(&swapped[0], "dmg"),
+            weapon_values(&heroes[1], "dmg")
+        );
+        assert_eq!(swapped[2].data, heroes[2].data);
+        for (tier, entry) in tier_entries(&swapped[0], "weapon").iter().enumerate() {
+            assert_eq!(
+                entry.get("name"),
+                Some(&[format!("\"crusader_weapon_{}\"", tier)][..])
+            );
+        }
+        assert_eq!(swapped[0].data[0], heroes[0].data[0]);
+    }
+
+    #[test]
+    fn test_perturb_keeps_tiers_in_order() {
+        let hero = test_hero("crusader", &[(6, 1), (7, 1), (8, 2), (9, 2), (10, 3)]);
+        let options = EquipmentOptions {
+            mode: EquipmentMode::Perturb,
+            variance: 90,
+        };
+        let levels = UpgradeLevels::from([(String::from("crusader.weapon"), 4)]);
+
+        for seed_val in ["testseed00", "testseed01", "testseed02"] {
+            let perturbed = randomize(
+                vec![hero.clone()],
+                &levels,
+                &options,
+                seed::create_rng(seed_val),
+            );
+            let dmg = weapon_values(&perturbed[0], "dmg");
+            let spd = weapon_values(&perturbed[0], "spd");
+            for tier in 1..dmg.len() {
+                assert!(dmg[tier][0] > dmg[tier - 1][0] && dmg[tier][1] > dmg[tier - 1][1]);
+            }
+            // equal base speeds stay equal and increases stay increases
+            assert_eq!(spd[1], spd[0]);
+            assert!(spd[2][0] > spd[1][0]);
+            assert!(dmg.iter().chain(&spd).flatten().all(|v| *v >= 1.0));
+            // zero stats stay zero
+            assert!(
+                weapon_values(&perturbed[0], "atk")
+                    .iter()
+                    .flatten()
+                    .all(|v| *v == 0.0)
+            );
+        }
+    }
+
+    #[test]
+    fn test_equipment_trees() {
+        let data: Value = serde_json::from_str(
+            r#"{"trees": [
+                {"id": "crusader.weapon", "requirements": [{"code": "a"}, {"code": "b"}]},
+                {"id": "crusader.armour", "requirements": [{"code": "a"}]},
+                {"id": "crusader.smite", "requirements": [{"code": "a"}, {"code": "b"}]}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            equipment_trees(&data),
+            UpgradeLevels::from([
+                (String::from("crusader.armour"), 1),
+                (String::from("crusader.weapon"), 2),
+            ])
+        );
+    }
+}
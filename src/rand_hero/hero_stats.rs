@@ -0,0 +1,215 @@
+use log::*;
+use rand::Rng;
+
+use crate::darkest::{Entry, Number};
+use crate::rand_hero::combat_skills::Hero;
+use crate::seed::SeedRng;
+
+/// Options for hero base stat randomization
+#[derive(Debug, Clone)]
+pub struct StatOptions {
+    /// Maximum change to any stat as a percentage of its base value
+    pub variance: u32,
+}
+
+/// Hero base stats which share one set of percentage changes per class
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stat {
+    Hp,
+    Dodge,
+    Prot,
+    Speed,
+    Crit,
+}
+
+impl Stat {
+    const ALL: [Stat; 5] = [Stat::Hp, Stat::Dodge, Stat::Prot, Stat::Speed, Stat::Crit];
+
+    /// Line key and property name of the stat within the hero file
+    /// Every weapon or armour tier line holds the stat so all tiers are adjusted together.
+    fn location(self) -> (&'static str, &'static str) {
+        match self {
+            Stat::Hp => ("armour", "hp"),
+            Stat::Dodge => ("armour", "def"),
+            Stat::Prot => ("armour", "prot"),
+            Stat::Speed => ("weapon", "spd"),
+            Stat::Crit => ("weapon", "crit"),
+        }
+    }
+
+    /// Bounds keeping the randomized values playable
+    fn bounds(self) -> (f64, f64) {
+        match self {
+            Stat::Hp => (1.0, 200.0),
+            Stat::Dodge => (0.0, 100.0),
+            Stat::Prot => (0.0, 95.0),
+            Stat::Speed => (0.0, 20.0),
+            Stat::Crit => (0.0, 100.0),
+        }
+    }
+}
+
+/// Bounds for every resistance on the `resistances` line
+const RESISTANCE_BOUNDS: (f64, f64) = (0.0, 200.0);
+
+/// Randomize the base stats and resistances of each hero
+/// The percentage changes for each class average to zero, so a stat raised by 10% is offset by
+/// others lowered by 10% in total. Stats differ in scale and values are rounded and bounded, so the
+/// absolute stat totals of a class are not kept.
+pub fn randomize(heroes: Vec<Hero>, options: &StatOptions, rng: SeedRng) -> Vec<Hero> {
+    info!("Randomizing hero stats");
+    let mut seed_rng: SeedRng = rng;
+    let variance = f64::from(options.variance) / 100.0;
+
+    let mut randomized: Vec<Hero> = Vec::new();
+    for mut hero in heroes {
+        let entries: Vec<Entry> = hero.data.iter().filter_map(|l| Entry::parse(l)).collect();
+
+        // stats with a base value of zero cannot be scaled so leave them out of the changes
+        let stats: Vec<Stat> = Stat::ALL
+            .into_iter()
+            .filter(|stat| {
+                let (key, prop) = stat.location();
+                first_value(&entries, key, prop).is_some_and(|v| v > 0.0)
+            })
+            .collect();
+        let stat_factors = budget_factors(stats.len(), variance, &mut seed_rng);
+
+        let resistances: Vec<String> = entries
+            .iter()
+            .find(|e| e.key == "resistances")
+            .map(|e| {
+                e.props
+                    .iter()
+                    .filter(|(_, values)| first_number(values).is_some_and(|n| n.value > 0.0))
+                    .map(|(name, _)| name.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let resistance_factors = budget_factors(resistances.len(), variance, &mut seed_rng);
+
+        for line in hero.data.iter_mut() {
+            let Some(mut entry) = Entry::parse(line) else {
+                continue;
+            };
+            let mut changed = false;
+            for (stat, factor) in stats.iter().zip(&stat_factors) {
+                let (key, prop) = stat.location();
+                if entry.key == key {
+                    changed |= scale_property(&mut entry, prop, *factor, stat.bounds());
+                }
+            }
+            if entry.key == "resistances" {
+                for (name, factor) in resistances.iter().zip(&resistance_factors) {
+                    changed |= scale_property(&mut entry, name, *factor, RESISTANCE_BOUNDS);
+                }
+            }
+            // only rewrite lines that were adjusted to keep everything else byte for byte
+            if changed {
+                *line = entry.to_string();
+            }
+        }
+
+        debug!(
+            "{} stat factors {:?} {:?}, resistance factors {:?} {:?}",
+            &hero.name, &stats, &stat_factors, &resistances, &resistance_factors
+        );
+        randomized.push(hero);
+    }
+
+    randomized
+}
+
+/// Random multipliers within the variance whose relative changes sum to zero
+fn budget_factors(count: usize, variance: f64, seed_rng: &mut SeedRng) -> Vec<f64> {
+    if count == 0 {
+        return Vec::new();
+    }
+
+    let deltas: Vec<f64> = (0..count)
+        .map(|_| seed_rng.random_range(-variance..=variance))
+        .collect();
+    let mean = deltas.iter().sum::<f64>() / count as f64;
+    let centered: Vec<f64> = deltas.iter().map(|d| d - mean).collect();
+
+    // centering can push a change past the variance, scale everything down evenly to keep the sum at zero
+    let largest = centered.iter().fold(0.0_f64, |acc, d| acc.max(d.abs()));
+    let scale = if largest > variance {
+        variance / largest
+    } else {
+        1.0
+    };
+
+    centered.iter().map(|d| 1.0 + d * scale).collect()
+}
+
+/// Scale the first value of a property, returns whether the entry was changed
+fn scale_property(entry: &mut Entry, prop: &str, factor: f64, bounds: (f64, f64)) -> bool {
+    let Some(values) = entry.get_mut(prop) else {
+        return false;
+    };
+    let Some(mut number) = values.first().and_then(|v| Number::parse(v)) else {
+        return false;
+    };
+    number.value = (number.value * factor).round().clamp(bounds.0, bounds.1);
+    values[0] = number.to_string();
+    true
+}
+
+fn first_number(values: &[String]) -> Option<Number> {
+    values.first().and_then(|v| Number::parse(v))
+}
+
+/// Value of a property on the first line with the given key, e.g. the lowest weapon or armour tier
+fn first_value(entries: &[Entry], key: &str, prop: &str) -> Option<f64> {
+    entries
+        .iter()
+        .find(|e| e.key == key)
+        .and_then(|e| e.get(prop))
+        .and_then(first_number)
+        .map(|n| n.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed;
+
+    #[test]
+    fn test_budget_factors_average_to_one() {
+        let mut test_seed_rng = seed::create_rng("testseed00");
+        let factors = budget_factors(5, 0.2, &mut test_seed_rng);
+
+        assert_eq!(factors.len(), 5);
+        assert!((factors.iter().sum::<f64>() - 5.0).abs() < 1e-9);
+        assert!(
+            factors
+                .iter()
+                .all(|f| (0.8 - 1e-9..=1.2 + 1e-9).contains(f))
+        );
+    }
+
+    #[test]
+    fn test_budget_factors_no_variance() {
+        let mut test_seed_rng = seed::create_rng("testseed00");
+        assert_eq!(budget_factors(3, 0.0, &mut test_seed_rng), vec![1.0; 3]);
+        assert!(budget_factors(0, 0.2, &mut test_seed_rng).is_empty());
+    }
+
+    #[test]
+    fn test_scale_property_bounds() {
+        let mut entry = Entry::parse(r#"armour: .name "test_armour_0" .def 5% .hp 33"#).unwrap();
+        assert!(scale_property(&mut entry, "def", 0.5, Stat::Dodge.bounds()));
+        assert!(scale_property(&mut entry, "hp", 10.0, Stat::Hp.bounds()));
+        assert!(!scale_property(
+            &mut entry,
+            "prot",
+            2.0,
+            Stat::Prot.bounds()
+        ));
+        assert_eq!(
+            entry.to_string(),
+            r#"armour: .name "test_armour_0" .def 3% .hp 200"#
+        );
+    }
+}
@@ -1,2 +1,4 @@
 pub mod camping_skills;
 pub mod combat_skills;
+pub mod equipment;
+pub mod hero_stats;
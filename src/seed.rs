@@ -1,13 +1,28 @@
-use chrono::Datelike;
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveTime, Utc};
 use log::debug;
-use rand::{Rng, distr::Alphanumeric, rng, rngs::StdRng};
+use rand::{Rng, distr::Alphanumeric, rng};
+use rand_chacha::ChaCha12Rng;
 use rand_pcg::Pcg64;
 use rand_seeder::Seeder;
 
-pub fn create_rng(seed: &str) -> StdRng {
+/// Random number generator used by all randomizers
+/// `StdRng` is not guaranteed to be stable between rand releases, so the algorithm is named explicitly.
+/// Mods generated before generator versions were recorded count as generator version 0.
+pub type SeedRng = ChaCha12Rng;
+
+/// Version of the generator algorithm and seeding scheme recorded with each seed
+/// Increase this whenever a change causes the same seed to produce a different mod.
+pub const GENERATOR_VERSION: u32 = 3;
+
+pub fn create_rng(seed: &str) -> SeedRng {
     Seeder::from(seed).into_rng()
 }
 
+/// Check whether a seed recorded with the given generator version reproduces the same mod
+pub fn is_compatible(generator_version: u32) -> bool {
+    generator_version == GENERATOR_VERSION
+}
+
 pub fn generate_seed() -> String {
     rng()
         .sample_iter(&Alphanumeric)
@@ -16,23 +31,212 @@ pub fn generate_seed() -> String {
         .collect::<String>()
 }
 
-pub fn generate_weekly_seed() -> String {
-    let current_date = chrono::Local::now().date_naive();
-    let current_week = current_date.iso_week().week0();
-    debug!("Current week: {}", current_week);
-    let week_seed = format!("{}{}seedoftheweek", current_date.year(), current_week);
-    debug!("Weekly base seed: {}", &week_seed);
-    let week_rng: Pcg64 = Seeder::from(week_seed).into_rng();
-    let wseed = week_rng
+/// Period a challenge seed is shared for, every period starts and ends at midnight UTC
+/// so all players get the same seed regardless of their timezone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChallengePeriod {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl ChallengePeriod {
+    pub const ALL: [ChallengePeriod; 3] = [
+        ChallengePeriod::Daily,
+        ChallengePeriod::Weekly,
+        ChallengePeriod::Monthly,
+    ];
+
+    /// String the challenge seed is generated from
+    /// The weekly seed uses the year of the ISO week, so the days of a week spanning new year share
+    /// one seed.
+    fn base_seed(self, date: NaiveDate) -> String {
+        match self {
+            ChallengePeriod::Daily => format!("{}{}seedoftheday", date.year(), date.ordinal0()),
+            ChallengePeriod::Weekly => {
+                format!(
+                    "{}{}seedoftheweek",
+                    date.iso_week().year(),
+                    date.iso_week().week0()
+                )
+            }
+            ChallengePeriod::Monthly => {
+                format!("{}{}seedofthemonth", date.year(), date.month0())
+            }
+        }
+    }
+
+    /// Readable name of the period containing the date
+    fn name(self, date: NaiveDate) -> String {
+        match self {
+            ChallengePeriod::Daily => format!("Daily {}", date.format("%Y-%m-%d")),
+            ChallengePeriod::Weekly => format!("Weekly {}", date.format("%G-W%V")),
+            ChallengePeriod::Monthly => format!("Monthly {}", date.format("%Y-%m")),
+        }
+    }
+
+    /// First day of the next period
+    fn next_start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            ChallengePeriod::Daily => date + Days::new(1),
+            ChallengePeriod::Weekly => {
+                date + Days::new(7 - u64::from(date.weekday().num_days_from_monday()))
+            }
+            ChallengePeriod::Monthly => {
+                let (year, month) = if date.month() == 12 {
+                    (date.year() + 1, 1)
+                } else {
+                    (date.year(), date.month() + 1)
+                };
+                NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(date)
+            }
+        }
+    }
+}
+
+/// A seed shared by all players for the current challenge period
+#[derive(Debug, Clone, PartialEq)]
+pub struct Challenge {
+    pub period: ChallengePeriod,
+    pub name: String,
+    pub seed: String,
+    pub ends: DateTime<Utc>,
+}
+
+impl Challenge {
+    /// Time left until the challenge ends, e.g. `2d 4h 13m`
+    pub fn remaining(&self, now: DateTime<Utc>) -> String {
+        let minutes = (self.ends - now).num_minutes().max(0);
+        let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+        if days > 0 {
+            format!("{}d {}h {}m", days, hours, minutes)
+        } else {
+            format!("{}h {}m", hours, minutes)
+        }
+    }
+}
+
+/// The challenge of the given period at the given time
+pub fn challenge_at(period: ChallengePeriod, now: DateTime<Utc>) -> Challenge {
+    let date = now.date_naive();
+    let base_seed = period.base_seed(date);
+    debug!("{:?} base seed: {}", period, &base_seed);
+    let challenge_rng: Pcg64 = Seeder::from(base_seed).into_rng();
+    let seed = challenge_rng
         .sample_iter(&Alphanumeric)
         .map(char::from)
         .take(32)
         .collect::<String>();
-    debug!("Seed of week {}: {}", current_week, wseed);
+    let ends = period.next_start(date).and_time(NaiveTime::MIN).and_utc();
+    debug!("Seed of {}: {}", period.name(date), seed);
+
+    Challenge {
+        period,
+        name: period.name(date),
+        seed,
+        ends,
+    }
+}
 
-    wseed
+pub fn generate_challenge_seed(period: ChallengePeriod) -> Challenge {
+    challenge_at(period, Utc::now())
 }
 
-// #[cfg(test)]
-// This file does not require testing since it is a simple wrapper around the rand library.
-// Refer to https://docs.rs/rand/latest/rand/ for library specific documentation and tests.
+/// The current challenge using the seed, if any
+pub fn active_challenge(seed: &str, now: DateTime<Utc>) -> Option<Challenge> {
+    ChallengePeriod::ALL
+        .into_iter()
+        .map(|period| challenge_at(period, now))
+        .find(|challenge| challenge.seed == seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    #[test]
+    fn test_create_rng_stable() {
+        // these values must not change for the same generator version
+        // if this fails the generator changed and GENERATOR_VERSION needs to be increased
+        let mut test_seed_rng = create_rng("testseed00");
+        let values: Vec<u32> = (0..4).map(|_| test_seed_rng.next_u32()).collect();
+        assert_eq!(values, vec![323477943, 469169011, 771970462, 2798845298]);
+    }
+
+    fn test_time(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_challenge_base_seeds() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        assert_eq!(
+            ChallengePeriod::Weekly.base_seed(date),
+            "202641seedoftheweek"
+        );
+        assert_eq!(
+            ChallengePeriod::Daily.base_seed(date),
+            "2026290seedoftheday"
+        );
+        assert_eq!(
+            ChallengePeriod::Monthly.base_seed(date),
+            "20269seedofthemonth"
+        );
+    }
+
+    #[test]
+    fn test_weekly_challenge_across_new_year() {
+        let week: Vec<NaiveDate> = [(2026, 12, 31), (2027, 1, 1), (2027, 1, 3)]
+            .into_iter()
+            .map(|(y, m, d)| NaiveDate::from_ymd_opt(y, m, d).unwrap())
+            .collect();
+        for date in &week {
+            assert_eq!(
+                ChallengePeriod::Weekly.base_seed(*date),
+                "202652seedoftheweek"
+            );
+            assert_eq!(ChallengePeriod::Weekly.name(*date), "Weekly 2026-W53");
+        }
+        let next = NaiveDate::from_ymd_opt(2027, 1, 4).unwrap();
+        assert_eq!(ChallengePeriod::Weekly.next_start(week[0]), next);
+        assert_eq!(
+            ChallengePeriod::Weekly.base_seed(next),
+            "20270seedoftheweek"
+        );
+    }
+
+    #[test]
+    fn test_challenge_periods_end_at_utc_midnight() {
+        let now = test_time("2026-10-18T23:30:00Z");
+        let daily = challenge_at(ChallengePeriod::Daily, now);
+        assert_eq!(daily.name, "Daily 2026-10-18");
+        assert_eq!(daily.ends, test_time("2026-10-19T00:00:00Z"));
+        assert_eq!(daily.remaining(now), "0h 30m");
+
+        let weekly = challenge_at(ChallengePeriod::Weekly, now);
+        assert_eq!(weekly.name, "Weekly 2026-W42");
+        assert_eq!(weekly.ends, test_time("2026-10-19T00:00:00Z"));
+
+        let monthly = challenge_at(ChallengePeriod::Monthly, test_time("2026-12-05T12:00:00Z"));
+        assert_eq!(monthly.ends, test_time("2027-01-01T00:00:00Z"));
+        assert_eq!(
+            monthly.remaining(test_time("2026-12-05T12:00:00Z")),
+            "26d 12h 0m"
+        );
+    }
+
+    #[test]
+    fn test_active_challenge() {
+        let now = test_time("2026-10-18T12:00:00Z");
+        let weekly = challenge_at(ChallengePeriod::Weekly, now);
+        assert_eq!(active_challenge(&weekly.seed, now), Some(weekly));
+        assert_eq!(active_challenge("testseed00", now), None);
+        // the same instant in a timezone ahead of UTC must still give the same challenge
+        let offset = DateTime::parse_from_rfc3339("2026-10-19T01:00:00+13:00").unwrap();
+        assert_eq!(
+            challenge_at(ChallengePeriod::Daily, offset.to_utc()).seed,
+            challenge_at(ChallengePeriod::Daily, now).seed
+        );
+    }
+}
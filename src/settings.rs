@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+
+use crate::rand_enemy::curio::CurioOptions;
+use crate::rand_enemy::loot::LootOptions;
+use crate::rand_enemy::mash::MashOptions;
+use crate::rand_enemy::monster::{Difficulty, MonsterOptions};
+use crate::rand_enemy::threat::BalanceOptions;
+use crate::rand_hero::camping_skills::CampingOptions;
+use crate::rand_hero::equipment::{EquipmentMode, EquipmentOptions};
+use crate::rand_hero::hero_stats::StatOptions;
+
+/// Randomization options selected in the GUI
+/// Stored with each seed so the same mod can be generated again.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    pub rand_boss: bool,
+    pub rand_monster: bool,
+    pub rand_stall: bool,
+    pub rand_named: bool,
+    pub rand_monster_stats: bool,
+    pub rand_monster_skills: bool,
+    pub include_courtyard: bool,
+    pub include_farmstead: bool,
+    pub rand_curios: bool,
+    pub rand_curio_outcomes: bool,
+    pub rand_loot: bool,
+    pub rand_camping_skills: bool,
+    pub rand_combat_skills: bool,
+    pub rand_hero_stats: bool,
+    pub rand_equipment: bool,
+    pub rand_modded_heroes: bool,
+    pub show_seed_in_game: bool,
+    pub camping_max_per_class: i32,
+    pub camping_min_per_class: i32,
+    pub camping_shared_skills: String,
+    pub camping_keep_owner_count: bool,
+    pub camping_shuffle_effects: bool,
+    pub camping_cost_jitter: i32,
+    pub stat_variance: i32,
+    pub equipment_mode: String,
+    pub monster_variance: i32,
+    pub monster_difficulty: String,
+    pub spawn_balance: String,
+    pub balance_tolerance: i32,
+    pub hard_ramp: i32,
+    pub loot_scale: i32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            rand_boss: false,
+            rand_monster: false,
+            rand_stall: false,
+            rand_named: false,
+            rand_monster_stats: false,
+            rand_monster_skills: false,
+            include_courtyard: false,
+            include_farmstead: false,
+            rand_curios: false,
+            rand_curio_outcomes: false,
+            rand_loot: false,
+            rand_camping_skills: false,
+            rand_combat_skills: false,
+            rand_hero_stats: false,
+            rand_equipment: false,
+            rand_modded_heroes: false,
+            show_seed_in_game: true,
+            camping_max_per_class: 4,
+            camping_min_per_class: 0,
+            camping_shared_skills: String::from("encourage, first_aid, pep_talk, hobby"),
+            camping_keep_owner_count: false,
+            camping_shuffle_effects: false,
+            camping_cost_jitter: 0,
+            stat_variance: 20,
+            equipment_mode: String::from("Swap"),
+            monster_variance: 20,
+            monster_difficulty: String::from("Normal"),
+            spawn_balance: String::from("Off"),
+            balance_tolerance: 25,
+            hard_ramp: 15,
+            loot_scale: 100,
+        }
+    }
+}
+
+impl Settings {
+    /// Fixed options used for every challenge seed
+    /// Modded heroes are excluded since they differ between players.
+    pub fn challenge() -> Settings {
+        Settings {
+            rand_monster: true,
+            rand_camping_skills: true,
+            rand_combat_skills: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn randomizes_heroes(&self) -> bool {
+        self.rand_combat_skills || self.rand_hero_stats || self.rand_equipment
+    }
+
+    pub fn randomizes_spawns(&self) -> bool {
+        self.rand_boss || self.rand_monster || self.rand_stall || self.rand_named
+    }
+
+    pub fn mash_options(&self) -> MashOptions {
+        MashOptions {
+            boss: self.rand_boss,
+            monsters: self.rand_monster,
+            stall: self.rand_stall,
+            named: self.rand_named,
+        }
+    }
+
+    pub fn randomizes_monsters(&self) -> bool {
+        self.rand_monster_stats || self.rand_monster_skills
+    }
+
+    /// DLC dungeons whose spawns are mixed in with the base game dungeons
+    pub fn dlc_dungeons(&self) -> Vec<&'static str> {
+        [
+            ("courtyard", self.include_courtyard),
+            ("farmstead", self.include_farmstead),
+        ]
+        .into_iter()
+        .filter(|(_, included)| *included)
+        .map(|(name, _)| name)
+        .collect()
+    }
+
+    pub fn curio_options(&self) -> CurioOptions {
+        CurioOptions {
+            shuffle_outcomes: self.rand_curio_outcomes,
+        }
+    }
+
+    pub fn randomizes_loot(&self) -> bool {
+        self.rand_loot || self.loot_options().scale != 100
+    }
+
+    pub fn loot_options(&self) -> LootOptions {
+        LootOptions {
+            shuffle: self.rand_loot,
+            scale: self.loot_scale.clamp(25, 400) as u32,
+        }
+    }
+
+    pub fn camping_options(&self) -> CampingOptions {
+        let max_per_class = self.camping_max_per_class.clamp(1, 7);
+        CampingOptions {
+            max_per_class: max_per_class as u8,
+            // a minimum above the limit could never be met
+            min_per_class: self.camping_min_per_class.clamp(0, max_per_class) as u8,
+            shared_skills: self
+                .camping_shared_skills
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            keep_owner_count: self.camping_keep_owner_count,
+            shuffle_effects: self.camping_shuffle_effects,
+            cost_jitter: self.camping_cost_jitter.clamp(0, 12) as u8,
+        }
+    }
+
+    pub fn stat_options(&self) -> StatOptions {
+        StatOptions {
+            variance: self.stat_variance.clamp(0, 100) as u32,
+        }
+    }
+
+    pub fn equipment_options(&self) -> EquipmentOptions {
+        EquipmentOptions {
+            mode: if self.equipment_mode == "Perturb" {
+                EquipmentMode::Perturb
+            } else {
+                EquipmentMode::Swap
+            },
+            variance: self.stat_variance.clamp(0, 100) as u32,
+        }
+    }
+
+    pub fn monster_options(&self) -> MonsterOptions {
+        MonsterOptions {
+            variance: self.monster_variance.clamp(0, 100) as u32,
+            difficulty: match self.monster_difficulty.as_str() {
+                "Easy" => Difficulty::Easy,
+                "Hard" => Difficulty::Hard,
+                _ => Difficulty::Normal,
+            },
+        }
+    }
+
+    /// Threat balancing for randomized spawns, `None` when spawns are shuffled freely
+    pub fn balance_options(&self) -> Option<BalanceOptions> {
+        let hard_ramp = match self.spawn_balance.as_str() {
+            "Balanced" => None,
+            "Hard" => Some(self.hard_ramp.clamp(0, 100) as u32),
+            _ => return None,
+        };
+        Some(BalanceOptions {
+            tolerance: self.balance_tolerance.clamp(0, 100) as u32,
+            hard_ramp,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_camping_min_never_above_max() {
+        let settings = Settings {
+            camping_max_per_class: 2,
+            camping_min_per_class: 5,
+            ..Default::default()
+        };
+        let options = settings.camping_options();
+        assert_eq!((options.min_per_class, options.max_per_class), (2, 2));
+
+        let settings = Settings {
+            camping_max_per_class: 12,
+            camping_min_per_class: -1,
+            ..Default::default()
+        };
+        let options = settings.camping_options();
+        assert_eq!((options.min_per_class, options.max_per_class), (0, 7));
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use crate::settings::Settings;
+
+/// Summary of the randomization results written alongside the mod
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Spoiler {
+    pub seed: String,
+    /// Generator algorithm version the seed was used with
+    pub generator: u32,
+    /// Options the mod was generated with, so an installed mod can be generated again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<Settings>,
+    /// Combat skills assigned to each class as `class.skill` of their original owner
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hero_skills: BTreeMap<String, Vec<String>>,
+    /// Class specific camping skills assigned to each class
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub camping_skills: BTreeMap<String, Vec<String>>,
+    /// Attack skills traded to each monster as `monster.skill` of their original owner
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub monster_skills: BTreeMap<String, Vec<String>>,
+    /// Monsters which can spawn in each dungeon level, e.g. `crypts.1`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dungeons: BTreeMap<String, Vec<String>>,
+    /// Monsters which can appear in each dungeon level when the party stalls
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub stall_encounters: BTreeMap<String, Vec<String>>,
+    /// Monsters of the named encounters in each dungeon level
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub named_encounters: BTreeMap<String, Vec<String>>,
+    /// Curios which can appear in each dungeon
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub curios: BTreeMap<String, Vec<String>>,
+    /// Table each loot table received its drops from
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub loot_tables: BTreeMap<String, Vec<String>>,
+}
+
+impl Spoiler {
+    /// Render the spoiler as JSON for the mod
+    pub fn render(&self) -> Result<String, Box<dyn Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Readable summary used for previews
+    pub fn summary(&self) -> String {
+        let mut summary = format!("Seed: {}\n", self.seed);
+        for (title, section) in [
+            ("Hero Skills", &self.hero_skills),
+            ("Camping Skills", &self.camping_skills),
+            ("Monster Skills", &self.monster_skills),
+            ("Dungeons", &self.dungeons),
+            ("Stall Encounters", &self.stall_encounters),
+            ("Named Encounters", &self.named_encounters),
+            ("Curios", &self.curios),
+            ("Loot Tables", &self.loot_tables),
+        ] {
+            if section.is_empty() {
+                continue;
+            }
+            summary.push_str(&format!("\n{}:\n", title));
+            for (name, values) in section {
+                summary.push_str(&format!("  {}: {}\n", name, values.join(", ")));
+            }
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_skips_empty_sections() {
+        let mut spoiler = Spoiler {
+            seed: String::from("testseed00"),
+            generator: 1,
+            ..Default::default()
+        };
+        spoiler.camping_skills.insert(
+            String::from("crusader"),
+            vec![String::from("zealous_speech"), String::from("bandage")],
+        );
+        assert_eq!(
+            spoiler.summary(),
+            "Seed: testseed00\n\nCamping Skills:\n  crusader: zealous_speech, bandage\n"
+        );
+    }
+}
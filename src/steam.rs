@@ -16,6 +16,15 @@ pub fn get_darkest_dungeon_install_path(
     Ok(install_path)
 }
 
+/// Steam build id of the installed game, used to tell which game version a mod was generated from
+pub fn get_game_build_id(app_id: u32) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+    let steam_dir = SteamDir::locate()?;
+    let (app, _) = steam_dir
+        .find_app(app_id)?
+        .ok_or("Darkest Dungeon not found in Steam library")?;
+    Ok(app.build_id)
+}
+
 // #[cfg(test)]
 // This file does not require testing since it is a simple wrapper around the steamlocate library.
 // Refer to https://github.com/williamvenner/steamlocate-rs for library specific documentation and tests.